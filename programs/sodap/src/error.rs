@@ -1,5 +1,3 @@
-// Please add your error types here, or move the content from state/error.rs
-
 use anchor_lang::prelude::*;
 
 #[error_code]
@@ -74,4 +72,32 @@ pub enum CustomError {
     InvalidLoyaltyPoints,
     #[msg("Transfer hook error")]
     TransferHookError,
+    #[msg("Insufficient escrow balance")]
+    InsufficientEscrowBalance,
+    #[msg("Invalid mint")]
+    InvalidMint,
+    #[msg("Invalid redemption")]
+    InvalidRedemption,
+    #[msg("Amount due exceeds the buyer's maximum amount")]
+    MaxAmountExceeded,
+    #[msg("Amount exceeds the store's approval threshold")]
+    ApprovalRequired,
+    #[msg("Admin has already approved this proposal")]
+    AlreadyApproved,
+    #[msg("Proposal does not have enough approvals")]
+    InsufficientApprovals,
+    #[msg("Store is suspended by the platform")]
+    StoreSuspended,
+    #[msg("Session daily limit exceeded")]
+    SessionLimitExceeded,
+    #[msg("Recovery timelock has not elapsed")]
+    RecoveryTimelockActive,
+    #[msg("Product attributes exceed the allowed count or length")]
+    InvalidProductAttributes,
+    #[msg("Product already exists")]
+    ProductAlreadyExists,
+    #[msg("Catalog page is full")]
+    CatalogPageFull,
+    #[msg("Invalid or unregistered barcode")]
+    InvalidBarcode,
 }
@@ -1,37 +1,82 @@
 use crate::error::CustomError;
-use crate::state::admin::PlatformAdmins;
-use crate::state::admin::{
-    AddPlatformAdmin, PlatformAdminAdded, PlatformAdminRemoved, RemovePlatformAdmin,
+pub use crate::state::admin::{
+    AddPlatformAdmin, InitializePlatform, PlatformAdmin, PlatformAdminAdded, PlatformAdminRemoved,
+    PlatformAdminStatusChanged, PlatformInitialized, RemovePlatformAdmin, RotateSuperAdmin,
+    SetPlatformAdminStatus, SuperAdminRotated,
 };
-
-use crate::utils::{check_root_password, is_super_root_admin};
+use crate::state::audit::AuditAction;
+use crate::types::PlatformAdminStatus;
 use anchor_lang::prelude::*;
 
+pub fn initialize_platform(ctx: Context<InitializePlatform>, super_admin: Pubkey) -> Result<()> {
+    let config = &mut ctx.accounts.platform_config;
+    config.super_admin = super_admin;
+    config.bump = ctx.bumps.platform_config;
+
+    let audit_log = &mut ctx.accounts.audit_log;
+    audit_log.scope = config.key();
+    audit_log.bump = ctx.bumps.audit_log;
+
+    emit!(PlatformInitialized {
+        super_admin,
+        initialized_at: Clock::get()?.unix_timestamp,
+    });
+    Ok(())
+}
+
+pub fn rotate_super_admin(ctx: Context<RotateSuperAdmin>) -> Result<()> {
+    let config = &mut ctx.accounts.platform_config;
+    let previous_super_admin = config.super_admin;
+    let new_super_admin = ctx.accounts.new_super_admin.key();
+    require!(
+        new_super_admin != previous_super_admin,
+        CustomError::InvalidParameters
+    );
+    config.super_admin = new_super_admin;
+
+    ctx.accounts.audit_log.record(
+        previous_super_admin,
+        AuditAction::SuperAdminRotated,
+        new_super_admin,
+    )?;
+
+    emit!(SuperAdminRotated {
+        previous_super_admin,
+        new_super_admin,
+        rotated_at: Clock::get()?.unix_timestamp,
+    });
+    Ok(())
+}
+
 pub fn add_platform_admin(
     ctx: Context<AddPlatformAdmin>,
-    new_admin: Pubkey,
-    username: String,
-    password: String,
+    admin_pubkey: Pubkey,
+    name: String,
 ) -> Result<()> {
-    let signer = ctx.accounts.signer.key();
-    let super_admin_pubkey = Pubkey::new_from_array([0u8; 32]); // Replace with actual super admin pubkey
-    require!(
-        is_super_root_admin(&signer, &super_admin_pubkey),
-        CustomError::Unauthorized
-    );
     require!(
-        check_root_password(&username, &password, "admin", "password"),
-        CustomError::Unauthorized
+        !name.is_empty() && name.len() <= PlatformAdmin::MAX_NAME_LEN,
+        CustomError::InvalidParameters
     );
-    require!(signer != new_admin, CustomError::Unauthorized);
-    let platform_admins = &mut ctx.accounts.platform_admins;
-    if platform_admins.admins.contains(&new_admin) {
-        return Err(CustomError::AdminAlreadyExists.into());
-    }
-    platform_admins.admins.push(new_admin);
+
+    let added_by = ctx.accounts.super_admin.key();
+    let added_at = Clock::get()?.unix_timestamp;
+
+    let platform_admin = &mut ctx.accounts.platform_admin;
+    platform_admin.admin_pubkey = admin_pubkey;
+    platform_admin.name = name.clone();
+    platform_admin.added_by = added_by;
+    platform_admin.added_at = added_at;
+    platform_admin.status = PlatformAdminStatus::Active;
+    platform_admin.bump = ctx.bumps.platform_admin;
+
+    ctx.accounts
+        .audit_log
+        .record(added_by, AuditAction::PlatformAdminAdded, admin_pubkey)?;
     emit!(PlatformAdminAdded {
-        admin_pubkey: new_admin,
-        added_at: Clock::get()?.unix_timestamp,
+        admin_pubkey,
+        name,
+        added_by,
+        added_at,
     });
     Ok(())
 }
@@ -39,28 +84,43 @@ pub fn add_platform_admin(
 pub fn remove_platform_admin(
     ctx: Context<RemovePlatformAdmin>,
     admin_pubkey: Pubkey,
-    username: String,
-    password: String,
 ) -> Result<()> {
-    let signer = ctx.accounts.signer.key();
-    let super_admin_pubkey = Pubkey::new_from_array([0u8; 32]); // Replace with actual super admin pubkey
-    require!(
-        is_super_root_admin(&signer, &super_admin_pubkey),
-        CustomError::Unauthorized
-    );
-    require!(
-        check_root_password(&username, &password, "admin", "password"),
-        CustomError::Unauthorized
-    );
-    require!(signer != admin_pubkey, CustomError::Unauthorized);
-    let platform_admins = &mut ctx.accounts.platform_admins;
-    if !platform_admins.admins.contains(&admin_pubkey) {
-        return Err(CustomError::AdminNotFound.into());
-    }
-    platform_admins.admins.retain(|a| a != &admin_pubkey);
+    // The admin's account is closed by the context, refunding the super admin
+    ctx.accounts.audit_log.record(
+        ctx.accounts.super_admin.key(),
+        AuditAction::PlatformAdminRemoved,
+        admin_pubkey,
+    )?;
     emit!(PlatformAdminRemoved {
         admin_pubkey,
         removed_at: Clock::get()?.unix_timestamp,
     });
     Ok(())
 }
+
+pub fn set_platform_admin_status(
+    ctx: Context<SetPlatformAdminStatus>,
+    admin_pubkey: Pubkey,
+    status: PlatformAdminStatus,
+) -> Result<()> {
+    let platform_admin = &mut ctx.accounts.platform_admin;
+    require!(
+        platform_admin.status != status,
+        CustomError::InvalidParameters
+    );
+    platform_admin.status = status;
+
+    let changed_by = ctx.accounts.super_admin.key();
+    ctx.accounts.audit_log.record(
+        changed_by,
+        AuditAction::PlatformAdminStatusChanged,
+        admin_pubkey,
+    )?;
+    emit!(PlatformAdminStatusChanged {
+        admin_pubkey,
+        status,
+        changed_by,
+        changed_at: Clock::get()?.unix_timestamp,
+    });
+    Ok(())
+}
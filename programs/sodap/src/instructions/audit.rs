@@ -0,0 +1,10 @@
+use crate::state::audit::InitializeAuditLog;
+use anchor_lang::prelude::*;
+
+/// Instruction to create the audit log of an existing store
+pub fn initialize_audit_log(ctx: Context<InitializeAuditLog>) -> Result<()> {
+    let audit_log = &mut ctx.accounts.audit_log;
+    audit_log.scope = ctx.accounts.store.key();
+    audit_log.bump = ctx.bumps.audit_log;
+    Ok(())
+}
@@ -0,0 +1,371 @@
+use crate::error::CustomError;
+use crate::state::audit::AuditAction;
+pub use crate::state::escrow::{
+    ApproveEscrowProposal, CancelEscrowProposal, Escrow, EscrowAction, EscrowPolicy,
+    EscrowPolicyUpdated, EscrowProposalApproved, EscrowProposalCancelled, EscrowProposalCreated,
+    EscrowProposalExecuted, ExecuteEscrowProposal, ProposeEscrowAction, RefundEscrow,
+    ReleaseEscrow, SetEscrowPolicy,
+};
+use crate::state::session::authorize_staff_or_session;
+use crate::types::StorePermission;
+use crate::utils::withdraw_lamports;
+use anchor_lang::prelude::*;
+
+pub fn release_escrow(ctx: Context<ReleaseEscrow>, amount: u64) -> Result<()> {
+    // Large releases must go through an approved proposal
+    charge_direct_payout(
+        &ctx.accounts.escrow_policy,
+        &mut ctx.accounts.escrow_account,
+        amount,
+    )?;
+
+    pay_out(
+        &mut ctx.accounts.escrow_account,
+        &ctx.accounts.store_owner,
+        amount,
+    )?;
+    ctx.accounts.audit_log.record(
+        ctx.accounts.authority.key(),
+        AuditAction::EscrowReleased,
+        ctx.accounts.store_owner.key(),
+    )?;
+
+    msg!("Released {} lamports from escrow to store owner", amount);
+    msg!(
+        "Remaining escrow balance: {}",
+        ctx.accounts.escrow_account.balance
+    );
+
+    Ok(())
+}
+
+pub fn refund_from_escrow(ctx: Context<RefundEscrow>, amount: u64) -> Result<()> {
+    authorize_staff_or_session(
+        &ctx.accounts.store,
+        &ctx.accounts.authority.key(),
+        ctx.accounts.pos_session.as_mut(),
+        StorePermission::IssueRefunds,
+        amount,
+    )?;
+
+    // Refunds can't exceed what the buyer paid on the order
+    let receipt = &mut ctx.accounts.receipt;
+    require!(
+        amount <= receipt.refundable(),
+        CustomError::InvalidParameters
+    );
+    receipt.refunded = receipt
+        .refunded
+        .checked_add(amount)
+        .ok_or(CustomError::ArithmeticError)?;
+
+    // Large refunds must go through an approved proposal
+    charge_direct_payout(
+        &ctx.accounts.escrow_policy,
+        &mut ctx.accounts.escrow_account,
+        amount,
+    )?;
+
+    pay_out(
+        &mut ctx.accounts.escrow_account,
+        &ctx.accounts.buyer,
+        amount,
+    )?;
+    ctx.accounts.audit_log.record(
+        ctx.accounts.authority.key(),
+        AuditAction::EscrowRefunded,
+        ctx.accounts.buyer.key(),
+    )?;
+
+    msg!("Refunded {} lamports from escrow to buyer", amount);
+    msg!(
+        "Remaining escrow balance: {}",
+        ctx.accounts.escrow_account.balance
+    );
+
+    Ok(())
+}
+
+/// Instruction for the store owner to configure the approval policy. Once set,
+/// the owner alone may only tighten it; loosening goes through a proposal.
+pub fn set_escrow_policy(
+    ctx: Context<SetEscrowPolicy>,
+    threshold: u64,
+    required_approvals: u8,
+) -> Result<()> {
+    validate_policy(required_approvals)?;
+
+    let policy = &mut ctx.accounts.escrow_policy;
+    if policy.store == Pubkey::default() {
+        policy.store = ctx.accounts.store.key();
+        policy.bump = ctx.bumps.escrow_policy;
+    } else {
+        require!(
+            threshold <= policy.threshold && required_approvals >= policy.required_approvals,
+            CustomError::ApprovalRequired
+        );
+    }
+
+    apply_policy(policy, threshold, required_approvals)?;
+
+    ctx.accounts.audit_log.record(
+        ctx.accounts.owner.key(),
+        AuditAction::EscrowPolicyUpdated,
+        ctx.accounts.escrow_policy.key(),
+    )
+}
+
+/// Instruction for a store admin to open a proposal; the proposer's approval is
+/// recorded straight away
+pub fn propose_escrow_action(
+    ctx: Context<ProposeEscrowAction>,
+    action: EscrowAction,
+) -> Result<()> {
+    let store = &ctx.accounts.store;
+    let proposer = ctx.accounts.proposer.key();
+
+    require!(
+        store.has_permission(&proposer, action.required_permission()),
+        CustomError::Unauthorized
+    );
+    if let EscrowAction::UpdatePolicy {
+        required_approvals, ..
+    } = action
+    {
+        validate_policy(required_approvals)?;
+    }
+
+    let policy = &mut ctx.accounts.escrow_policy;
+    let proposal_id = policy.proposal_count;
+    policy.proposal_count = proposal_id
+        .checked_add(1)
+        .ok_or(CustomError::ArithmeticError)?;
+
+    let proposal = &mut ctx.accounts.proposal;
+    proposal.store = store.key();
+    proposal.proposal_id = proposal_id;
+    proposal.proposer = proposer;
+    proposal.action = action.clone();
+    proposal.approvals = vec![proposer];
+    proposal.created_at = Clock::get()?.unix_timestamp;
+    proposal.bump = ctx.bumps.proposal;
+
+    ctx.accounts
+        .audit_log
+        .record(proposer, AuditAction::EscrowProposalCreated, proposal.key())?;
+
+    emit!(EscrowProposalCreated {
+        store: store.key(),
+        proposal_id,
+        proposer,
+        action,
+        created_at: proposal.created_at,
+    });
+
+    Ok(())
+}
+
+/// Instruction for a store admin to approve a pending proposal
+pub fn approve_escrow_proposal(ctx: Context<ApproveEscrowProposal>) -> Result<()> {
+    let store = &ctx.accounts.store;
+    let proposal = &mut ctx.accounts.proposal;
+    let approver = ctx.accounts.approver.key();
+
+    require!(
+        store.has_permission(&approver, proposal.action.required_permission()),
+        CustomError::Unauthorized
+    );
+    require!(
+        !proposal.approvals.contains(&approver),
+        CustomError::AlreadyApproved
+    );
+    require!(
+        proposal.approvals.len() < EscrowPolicy::MAX_APPROVALS as usize,
+        CustomError::InvalidParameters
+    );
+    proposal.approvals.push(approver);
+
+    ctx.accounts.audit_log.record(
+        approver,
+        AuditAction::EscrowProposalApproved,
+        proposal.key(),
+    )?;
+
+    emit!(EscrowProposalApproved {
+        store: store.key(),
+        proposal_id: proposal.proposal_id,
+        approver,
+        approvals: proposal.approvals.len() as u8,
+    });
+
+    Ok(())
+}
+
+/// Instruction to carry out a proposal once enough current admins approved it
+pub fn execute_escrow_proposal(ctx: Context<ExecuteEscrowProposal>) -> Result<()> {
+    let store = &ctx.accounts.store;
+    let proposal = &ctx.accounts.proposal;
+
+    // Approvals are recounted against the current roster and policy, so
+    // revoked admins no longer count
+    require!(
+        proposal.valid_approvals(store) >= ctx.accounts.escrow_policy.required_approvals as usize,
+        CustomError::InsufficientApprovals
+    );
+
+    match proposal.action {
+        EscrowAction::Release { amount } => {
+            // Suspended stores can't pay themselves
+            require!(!store.is_suspended(), CustomError::StoreSuspended);
+            let recipient = ctx
+                .accounts
+                .recipient
+                .as_ref()
+                .ok_or(CustomError::InvalidParameters)?;
+            require!(recipient.key() == store.owner, CustomError::Unauthorized);
+            let escrow = ctx
+                .accounts
+                .escrow_account
+                .as_mut()
+                .ok_or(CustomError::EscrowNotFound)?;
+            pay_out(escrow, recipient, amount)?;
+        }
+        EscrowAction::Refund { receipt, amount } => {
+            // Suspension leaves only direct refunds under the payout cap;
+            // refunds large enough to need approval wait for it to lift
+            require!(!store.is_suspended(), CustomError::StoreSuspended);
+            // Same limits as a direct refund: the buyer on the receipt, up to
+            // what is left to refund on the order
+            let purchase = ctx
+                .accounts
+                .receipt
+                .as_mut()
+                .ok_or(CustomError::InvalidParameters)?;
+            require!(purchase.key() == receipt, CustomError::InvalidParameters);
+            require!(
+                amount <= purchase.refundable(),
+                CustomError::InvalidParameters
+            );
+            purchase.refunded = purchase
+                .refunded
+                .checked_add(amount)
+                .ok_or(CustomError::ArithmeticError)?;
+            let recipient = ctx
+                .accounts
+                .recipient
+                .as_ref()
+                .ok_or(CustomError::InvalidParameters)?;
+            require!(recipient.key() == purchase.buyer, CustomError::Unauthorized);
+            let escrow = ctx
+                .accounts
+                .escrow_account
+                .as_mut()
+                .ok_or(CustomError::EscrowNotFound)?;
+            pay_out(escrow, recipient, amount)?;
+        }
+        EscrowAction::UpdatePolicy {
+            threshold,
+            required_approvals,
+        } => {
+            apply_policy(
+                &mut ctx.accounts.escrow_policy,
+                threshold,
+                required_approvals,
+            )?;
+        }
+    }
+
+    // Execution is permissionless, so the proposal itself is recorded as the actor
+    ctx.accounts.audit_log.record(
+        proposal.key(),
+        AuditAction::EscrowProposalExecuted,
+        ctx.accounts
+            .recipient
+            .as_ref()
+            .map_or(ctx.accounts.escrow_policy.key(), |r| r.key()),
+    )?;
+
+    emit!(EscrowProposalExecuted {
+        store: store.key(),
+        proposal_id: proposal.proposal_id,
+        action: proposal.action.clone(),
+        executed_at: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+/// Instruction for the proposer or the store owner to withdraw a proposal
+pub fn cancel_escrow_proposal(ctx: Context<CancelEscrowProposal>) -> Result<()> {
+    let authority = ctx.accounts.authority.key();
+    require!(
+        authority == ctx.accounts.proposal.proposer || authority == ctx.accounts.store.owner,
+        CustomError::Unauthorized
+    );
+
+    ctx.accounts.audit_log.record(
+        authority,
+        AuditAction::EscrowProposalCancelled,
+        ctx.accounts.proposal.key(),
+    )?;
+
+    emit!(EscrowProposalCancelled {
+        store: ctx.accounts.store.key(),
+        proposal_id: ctx.accounts.proposal.proposal_id,
+        cancelled_by: authority,
+        cancelled_at: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+/// Applies the store's policy to a payout made without a proposal; stores
+/// without a policy are not limited
+pub(crate) fn charge_direct_payout(
+    policy_info: &AccountInfo,
+    escrow: &mut Escrow,
+    amount: u64,
+) -> Result<()> {
+    if let Some(policy) = EscrowPolicy::load(policy_info)? {
+        escrow.charge_direct_payout(amount, policy.threshold, Clock::get()?.unix_timestamp)?;
+    }
+    Ok(())
+}
+
+fn validate_policy(required_approvals: u8) -> Result<()> {
+    require!(
+        required_approvals > 0 && required_approvals <= EscrowPolicy::MAX_APPROVALS,
+        CustomError::InvalidParameters
+    );
+    Ok(())
+}
+
+fn apply_policy(policy: &mut EscrowPolicy, threshold: u64, required_approvals: u8) -> Result<()> {
+    policy.threshold = threshold;
+    policy.required_approvals = required_approvals;
+
+    emit!(EscrowPolicyUpdated {
+        store: policy.store,
+        threshold,
+        required_approvals,
+        updated_at: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+/// Moves `amount` out of the escrow PDA and keeps the tracked balance in step
+pub(crate) fn pay_out(escrow: &mut Account<Escrow>, to: &AccountInfo, amount: u64) -> Result<()> {
+    require!(
+        escrow.balance >= amount,
+        CustomError::InsufficientEscrowBalance
+    );
+
+    withdraw_lamports(&escrow.to_account_info(), to, amount)?;
+
+    escrow.balance = escrow
+        .balance
+        .checked_sub(amount)
+        .ok_or(CustomError::ArithmeticError)?;
+    Ok(())
+}
@@ -1,17 +1,17 @@
 use crate::error::CustomError;
-use crate::state::loyalty::LoyaltyMint;
-use crate::state::loyalty::{
-    InitializeLoyaltyMint as InitializeLoyaltyMintState,
-    MintLoyaltyTokens as MintLoyaltyTokensState, RedeemLoyaltyPoints as RedeemLoyaltyPointsState,
-};
-use crate::state::store::Store;
+use crate::instructions::escrow::{charge_direct_payout, pay_out};
+use crate::state::audit::AuditAction;
+pub use crate::state::loyalty::{InitializeLoyaltyMint, MintLoyaltyTokens, RedeemLoyaltyPoints};
+use crate::state::loyalty::{LoyaltyPointsEarned, LoyaltyPointsRedeemed};
+use crate::state::session::authorize_staff_or_session;
+use crate::types::StorePermission;
 use anchor_lang::prelude::*;
 use anchor_spl::{
     token::{self, MintTo},
-    token_2022,
     token_interface::{Mint, TokenAccount, TokenInterface},
 };
 
+#[allow(dead_code)]
 #[derive(Accounts)]
 pub struct LoyaltyTransferHook<'info> {
     #[account(mut)]
@@ -24,91 +24,129 @@ pub struct LoyaltyTransferHook<'info> {
     pub token_program: Interface<'info, TokenInterface>,
 }
 
-#[derive(Accounts)]
-pub struct InitializeLoyaltyMint<'info> {
-    #[account(mut)]
-    pub store: Account<'info, Store>,
-    #[account(
-        init,
-        payer = authority,
-        space = 8 + 32 + 32 + 8,
-        seeds = [b"loyalty_mint", store.key().as_ref()],
-        bump
-    )]
-    pub loyalty_mint: Account<'info, LoyaltyMint>,
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    pub system_program: Program<'info, System>,
-    pub token_program: Interface<'info, TokenInterface>,
-    pub rent: Sysvar<'info, Rent>,
-}
+pub fn initialize_loyalty_mint(
+    ctx: Context<InitializeLoyaltyMint>,
+    points_per_sol: u64,
+    redemption_rate: u64,
+    use_token2022: bool, // Flag for future Token-2022 compatibility
+) -> Result<()> {
+    // Initialize the loyalty mint tracking account
+    let loyalty_mint = &mut ctx.accounts.loyalty_mint_account;
+    loyalty_mint.store = ctx.accounts.store.key();
+    loyalty_mint.mint = ctx.accounts.token_mint.key();
+    loyalty_mint.authority = ctx.accounts.payer.key();
+    loyalty_mint.points_per_sol = points_per_sol;
+    loyalty_mint.redemption_rate = redemption_rate;
+    loyalty_mint.total_points_issued = 0;
+    loyalty_mint.total_points_redeemed = 0;
+    loyalty_mint.is_token2022 = use_token2022; // Save for future Token-2022 implementation
+    loyalty_mint.bump = ctx.bumps.loyalty_mint_account;
 
-#[derive(Accounts)]
-pub struct MintLoyaltyTokens<'info> {
-    pub store: Account<'info, Store>,
-    #[account(mut)]
-    pub loyalty_mint: Account<'info, LoyaltyMint>,
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    pub token_program: Interface<'info, TokenInterface>,
-    #[account(mut)]
-    pub destination: InterfaceAccount<'info, TokenAccount>,
-}
+    ctx.accounts.audit_log.record(
+        ctx.accounts.payer.key(),
+        AuditAction::LoyaltyMintInitialized,
+        ctx.accounts.token_mint.key(),
+    )?;
 
-#[derive(Accounts)]
-pub struct RedeemLoyaltyPoints<'info> {
-    pub store: Account<'info, Store>,
-    #[account(mut)]
-    pub loyalty_mint: Account<'info, LoyaltyMint>,
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    pub token_program: Interface<'info, TokenInterface>,
-    #[account(mut)]
-    pub source: InterfaceAccount<'info, TokenAccount>,
+    msg!(
+        "Initialized loyalty mint for store: {}",
+        ctx.accounts.store.key()
+    );
+    msg!("Points per SOL: {}", points_per_sol);
+    msg!("Redemption rate: {}", redemption_rate);
+    msg!("Mint address: {}", ctx.accounts.token_mint.key());
+    if use_token2022 {
+        msg!("Token-2022 support is marked for future implementation");
+    }
+
+    Ok(())
 }
 
-pub fn initialize_loyalty_mint(ctx: Context<InitializeLoyaltyMint>) -> Result<()> {
-    let loyalty_mint = &mut ctx.accounts.loyalty_mint;
-    loyalty_mint.store = ctx.accounts.store.key();
-    loyalty_mint.authority = ctx.accounts.authority.key();
-    loyalty_mint.total_supply = 0;
-
-    // Initialize Token2022 mint with TransferHook
-    token_2022::initialize_mint(
-        CpiContext::new(
-            ctx.accounts.token_program.to_account_info(),
-            token_2022::InitializeMint {
-                mint: ctx.accounts.loyalty_mint.to_account_info(),
-                rent: ctx.accounts.rent.to_account_info(),
-            },
-        ),
-        9, // 9 decimals like SOL
+pub fn mint_loyalty_points(ctx: Context<MintLoyaltyTokens>) -> Result<()> {
+    // Points are earned on what the receipt says was paid, never a caller amount
+    let purchase_amount_lamports = ctx.accounts.receipt.total_paid;
+    ctx.accounts.receipt.loyalty_credited = true;
+
+    // POS terminals credit in-store purchases, capped per day by purchase amount
+    authorize_staff_or_session(
+        &ctx.accounts.store,
         &ctx.accounts.authority.key(),
-        Some(&ctx.accounts.authority.key()),
+        ctx.accounts.pos_session.as_mut(),
+        StorePermission::MintLoyalty,
+        purchase_amount_lamports,
     )?;
 
-    Ok(())
-}
+    // Calculate how many loyalty points to mint based on purchase amount
+    let loyalty_mint = &mut ctx.accounts.loyalty_mint_account;
 
-pub fn mint_loyalty_points(
-    ctx: Context<MintLoyaltyTokens>,
-    amount: u64,
-    _destination: Pubkey,
-) -> Result<()> {
-    // First, perform the token mint operation
-    let mint_info = ctx.accounts.loyalty_mint.to_account_info();
-    let destination_info = ctx.accounts.destination.to_account_info();
-    let authority_info = ctx.accounts.authority.to_account_info();
-    let token_program_info = ctx.accounts.token_program.to_account_info();
+    // Convert lamports to SOL (1 SOL = 1_000_000_000 lamports)
+    let purchase_amount_sol = purchase_amount_lamports
+        .checked_div(1_000_000_000)
+        .ok_or(CustomError::ArithmeticError)?;
 
-    let cpi_accounts = MintTo {
-        mint: mint_info,
-        to: destination_info,
-        authority: authority_info,
+    // Calculate points to mint (points_per_sol * purchase_amount_sol)
+    // For decimal precision, we use the token's decimal places (6)
+    let points_to_mint = loyalty_mint
+        .points_per_sol
+        .checked_mul(purchase_amount_sol)
+        .ok_or(CustomError::ArithmeticError)?
+        .checked_mul(1_000_000) // Adjust for 6 decimal places in the token
+        .ok_or(CustomError::ArithmeticError)?;
+
+    // Check if points were calculated (zero check)
+    require!(points_to_mint > 0, CustomError::ArithmeticError);
+
+    // Mint tokens to the user's token account, signed by the loyalty mint PDA
+    let store_key = ctx.accounts.store.key();
+    let seeds = &[
+        b"loyalty_mint".as_ref(),
+        store_key.as_ref(),
+        &[loyalty_mint.bump],
+    ];
+    let signer_seeds = &[&seeds[..]];
+    let cpi_accounts = token::MintTo {
+        mint: ctx.accounts.token_mint.to_account_info(),
+        to: ctx.accounts.token_account.to_account_info(),
+        authority: loyalty_mint.to_account_info(),
     };
+    let cpi_program = ctx.accounts.token_program.to_account_info();
+    let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
+    token::mint_to(cpi_ctx, points_to_mint)?;
 
-    let cpi_ctx = CpiContext::new(token_program_info, cpi_accounts);
-    token::mint_to(cpi_ctx, amount)?;
+    // Update loyalty mint accounting
+    loyalty_mint.total_points_issued = loyalty_mint
+        .total_points_issued
+        .checked_add(points_to_mint)
+        .ok_or(CustomError::ArithmeticError)?;
+
+    ctx.accounts.audit_log.record(
+        ctx.accounts.authority.key(),
+        AuditAction::LoyaltyPointsMinted,
+        ctx.accounts.recipient.key(),
+    )?;
+    emit!(LoyaltyPointsEarned {
+        user: ctx.accounts.recipient.key(),
+        store_id: store_key,
+        points_earned: points_to_mint,
+        total_points: ctx
+            .accounts
+            .token_account
+            .amount
+            .saturating_add(points_to_mint),
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    // Log the operation
+    msg!(
+        "Minted {} loyalty points for purchase of {} SOL",
+        points_to_mint,
+        purchase_amount_sol
+    );
+    msg!("Recipient: {}", ctx.accounts.recipient.key());
+    msg!(
+        "Total points issued by store: {}",
+        loyalty_mint.total_points_issued
+    );
 
     Ok(())
 }
@@ -116,25 +154,120 @@ pub fn mint_loyalty_points(
 pub fn redeem_loyalty_points(
     ctx: Context<RedeemLoyaltyPoints>,
     points_to_redeem: u64,
+    redeem_for_sol: bool,
 ) -> Result<()> {
-    // First, perform the token burn operation
-    let mint_info = ctx.accounts.loyalty_mint.to_account_info();
-    let source_info = ctx.accounts.source.to_account_info();
-    let authority_info = ctx.accounts.authority.to_account_info();
-    let token_program_info = ctx.accounts.token_program.to_account_info();
+    // Get loyalty mint and validate redemption
+    let loyalty_mint = &mut ctx.accounts.loyalty_mint_account;
 
-    let cpi_accounts = token_2022::Burn {
-        mint: mint_info,
-        from: source_info,
-        authority: authority_info,
+    // Check if user has enough tokens to redeem
+    let user_token_balance = ctx.accounts.token_account.amount;
+    require!(
+        user_token_balance >= points_to_redeem,
+        CustomError::InsufficientLoyaltyPoints
+    );
+
+    // Calculate SOL value if redeeming for SOL
+    // Formula: (points_to_redeem / 1_000_000) / redemption_rate = SOL amount
+    let sol_value = if redeem_for_sol {
+        // Points have 6 decimal places, so divide by 1_000_000 to get the whole points
+        let whole_points = points_to_redeem
+            .checked_div(1_000_000)
+            .ok_or(CustomError::ArithmeticError)?;
+
+        // Calculate SOL value based on redemption rate
+        // (e.g., if redemption_rate is 100, then 100 points = 1 SOL)
+        whole_points
+            .checked_div(loyalty_mint.redemption_rate)
+            .ok_or(CustomError::ArithmeticError)?
+            .checked_mul(1_000_000_000) // Convert to lamports
+            .ok_or(CustomError::ArithmeticError)?
+    } else {
+        0 // Not redeeming for SOL
     };
 
-    let cpi_ctx = CpiContext::new(token_program_info, cpi_accounts);
-    token_2022::burn(cpi_ctx, points_to_redeem)?;
+    // SOL redemptions pay out of escrow, so they need the same sign-off as a release
+    let escrow_approver = if redeem_for_sol {
+        let approver = ctx
+            .accounts
+            .escrow_authority
+            .as_ref()
+            .ok_or(CustomError::Unauthorized)?
+            .key();
+        require!(
+            ctx.accounts
+                .store
+                .has_permission(&approver, StorePermission::ReleaseEscrow),
+            CustomError::Unauthorized
+        );
+        Some(approver)
+    } else {
+        None
+    };
+
+    // Burn the loyalty tokens
+    let cpi_accounts = token::Burn {
+        mint: ctx.accounts.token_mint.to_account_info(),
+        from: ctx.accounts.token_account.to_account_info(),
+        authority: ctx.accounts.user.to_account_info(),
+    };
+    let cpi_program = ctx.accounts.token_program.to_account_info();
+    let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+    token::burn(cpi_ctx, points_to_redeem)?;
+
+    // If redeeming for SOL, transfer from escrow to user
+    if let Some(approver) = escrow_approver {
+        // Ensure escrow account is provided
+        let escrow = ctx
+            .accounts
+            .escrow_account
+            .as_mut()
+            .ok_or(CustomError::InvalidRedemption)?;
+
+        // Transfer SOL from escrow to user, within the policy's daily limit
+        charge_direct_payout(&ctx.accounts.escrow_policy, escrow, sol_value)?;
+        pay_out(escrow, &ctx.accounts.user.to_account_info(), sol_value)?;
+        ctx.accounts.audit_log.record(
+            approver,
+            AuditAction::LoyaltyPointsRedeemed,
+            ctx.accounts.user.key(),
+        )?;
+
+        msg!(
+            "Redeemed {} loyalty points for {} SOL",
+            points_to_redeem,
+            sol_value / 1_000_000_000
+        );
+    } else {
+        msg!(
+            "Redeemed {} loyalty points for store products/services",
+            points_to_redeem
+        );
+    }
+
+    // Update loyalty mint accounting
+    loyalty_mint.total_points_redeemed = loyalty_mint
+        .total_points_redeemed
+        .checked_add(points_to_redeem)
+        .ok_or(CustomError::ArithmeticError)?;
+
+    emit!(LoyaltyPointsRedeemed {
+        user: ctx.accounts.user.key(),
+        store_id: ctx.accounts.store.key(),
+        points_redeemed: points_to_redeem,
+        discount_value: sol_value,
+        remaining_points: user_token_balance - points_to_redeem,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    msg!(
+        "Total points redeemed at store: {}",
+        loyalty_mint.total_points_redeemed
+    );
 
     Ok(())
 }
 
+#[allow(dead_code)]
 pub fn handle_transfer_hook(ctx: Context<LoyaltyTransferHook>, amount: u64) -> Result<()> {
     // First, perform the token mint operation
     let mint_info = ctx.accounts.mint.to_account_info();
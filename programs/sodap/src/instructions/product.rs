@@ -1,36 +1,23 @@
 use crate::error::CustomError;
+use crate::state::audit::{AuditAction, AuditLog};
+use crate::state::loyalty::LoyaltyPointsEarned;
 pub use crate::state::product::{
-    CartPurchased, DeactivateProduct, Product, PurchaseCart, RegisterProduct, UpdateProduct,
+    BatchRegisterProducts, BatchUpdateProducts, CartPurchased, CloseProduct, DeactivateProduct,
+    Product, ProductBatchEntry, ProductBatchItemFailed, ProductBatchProcessed, ProductBatchUpdate,
+    ProductVariant, Purchase, PurchaseCart, PurchaseCompleted, RegisterProduct, RegisterVariant,
+    SetProductBarcode, SkuEntry, UpdateProduct, UpdateVariant,
 };
-use crate::state::store::Store;
-use crate::state::{RefundEscrow, ReleaseEscrow};
-use crate::types::{TokenizedType, TransactionStatus};
+use crate::state::session::authorize_staff_or_session;
+use crate::state::{CatalogIndex, CatalogPage};
+use crate::types::{AnomalyFlag, StorePermission, TokenizedType, TransactionStatus};
+use crate::utils::resize_account;
+use crate::ProductAttribute;
 use anchor_lang::prelude::*;
-use anchor_lang::system_program::{self, Transfer};
-
-#[derive(Accounts)]
-pub struct StoreEscrow<'info> {
-    #[account(
-        init_if_needed,
-        payer = payer,
-        space = 8 + 32 + 8,
-        seeds = [b"escrow", store.key().as_ref()],
-        bump
-    )]
-    pub escrow_account: Account<'info, Escrow>,
-    pub store: Account<'info, Store>,
-    #[account(mut)]
-    pub payer: Signer<'info>,
-    pub system_program: Program<'info, System>,
-}
-
-#[account]
-pub struct Escrow {
-    pub store: Pubkey,
-    pub balance: u64,
-}
+use anchor_lang::system_program;
+use anchor_spl::token;
 
 // Product instructions
+#[allow(clippy::too_many_arguments)]
 pub fn register_product(
     ctx: Context<RegisterProduct>,
     product_uuid: [u8; 16],
@@ -38,7 +25,20 @@ pub fn register_product(
     stock: u64,
     tokenized_type: TokenizedType,
     metadata_uri: String,
+    attributes: Vec<ProductAttribute>,
+    barcode: Option<String>,
 ) -> Result<()> {
+    validate_metadata_uri(&metadata_uri)?;
+    validate_attributes(&attributes)?;
+    let store_key = ctx.accounts.store.key();
+    register_barcode(
+        ctx.accounts.sku_entry.as_deref_mut(),
+        ctx.bumps.sku_entry,
+        store_key,
+        product_uuid,
+        barcode.as_deref(),
+    )?;
+
     let product = &mut ctx.accounts.product;
     product.uuid = product_uuid;
     product.price = price;
@@ -48,6 +48,28 @@ pub fn register_product(
     product.is_active = true;
     product.store = ctx.accounts.store.key();
     product.authority = ctx.accounts.authority.key();
+    product.attributes = attributes;
+    product.barcode = barcode;
+    product.variant_count = 0;
+
+    let accounts = &mut *ctx.accounts;
+    open_catalog_page(
+        &mut accounts.catalog_index,
+        ctx.bumps.catalog_index,
+        &mut accounts.catalog_page,
+        ctx.bumps.catalog_page,
+        accounts.store.key(),
+        1,
+    );
+    accounts
+        .catalog_index
+        .push(&mut accounts.catalog_page, accounts.product.key())?;
+
+    accounts.audit_log.record(
+        accounts.authority.key(),
+        AuditAction::ProductRegistered,
+        accounts.product.key(),
+    )?;
 
     Ok(())
 }
@@ -59,7 +81,36 @@ pub fn update_product(
     new_stock: Option<u64>,
     new_metadata_uri: Option<String>,
     new_tokenized_type: Option<TokenizedType>,
+    new_attributes: Option<Vec<ProductAttribute>>,
 ) -> Result<()> {
+    let store = &ctx.accounts.store;
+    let authority = ctx.accounts.authority.key();
+
+    // Catalog changes and stock adjustments are separate permissions
+    if new_price.is_some()
+        || new_metadata_uri.is_some()
+        || new_tokenized_type.is_some()
+        || new_attributes.is_some()
+    {
+        require!(
+            store.has_permission(&authority, StorePermission::ManageProducts),
+            CustomError::Unauthorized
+        );
+    }
+    if new_stock.is_some() {
+        authorize_staff_or_session(
+            store,
+            &authority,
+            ctx.accounts.pos_session.as_mut(),
+            StorePermission::AdjustStock,
+            0,
+        )?;
+    }
+
+    if let Some(metadata_uri) = &new_metadata_uri {
+        validate_metadata_uri(metadata_uri)?;
+    }
+
     let product = &mut ctx.accounts.product;
 
     if let Some(price) = new_price {
@@ -78,89 +129,752 @@ pub fn update_product(
         product.tokenized_type = tokenized_type;
     }
 
+    // Resize the account to the new attribute set; the caller pays or is refunded
+    if let Some(attributes) = new_attributes {
+        validate_attributes(&attributes)?;
+        resize_account(
+            &product.to_account_info(),
+            &ctx.accounts.authority.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            Product::space(&attributes),
+        )?;
+        product.attributes = attributes;
+    }
+
+    ctx.accounts
+        .audit_log
+        .record(authority, AuditAction::ProductUpdated, product.key())?;
+
     Ok(())
 }
 
 pub fn deactivate_product(ctx: Context<DeactivateProduct>, _product_uuid: [u8; 16]) -> Result<()> {
     let product = &mut ctx.accounts.product;
     product.is_active = false;
+
+    ctx.accounts.audit_log.record(
+        ctx.accounts.authority.key(),
+        AuditAction::ProductDeactivated,
+        product.key(),
+    )?;
     Ok(())
 }
 
-/// Validate product cart items against remaining accounts and calculate total
+/// Creates several products in one transaction. Each entry's product PDA is
+/// passed in `remaining_accounts` in the same order, followed by its
+/// `SkuEntry` PDA when it has a barcode; entries that fail validation are
+/// skipped and reported through `ProductBatchItemFailed`.
+pub fn batch_register_products<'info>(
+    ctx: Context<'_, '_, 'info, 'info, BatchRegisterProducts<'info>>,
+    entries: Vec<ProductBatchEntry>,
+) -> Result<()> {
+    require!(
+        !entries.is_empty() && entries.len() <= Product::MAX_BATCH_SIZE,
+        CustomError::InvalidParameters
+    );
+    let barcoded = entries.iter().filter(|e| e.barcode.is_some()).count();
+    require!(
+        ctx.remaining_accounts.len() == entries.len() + barcoded,
+        CustomError::InvalidParameters
+    );
+
+    let store_key = ctx.accounts.store.key();
+    let authority = ctx.accounts.authority.to_account_info();
+    let system_program_info = ctx.accounts.system_program.to_account_info();
+    let accounts = &mut *ctx.accounts;
+    open_catalog_page(
+        &mut accounts.catalog_index,
+        ctx.bumps.catalog_index,
+        &mut accounts.catalog_page,
+        ctx.bumps.catalog_page,
+        store_key,
+        entries.len(),
+    );
+
+    let mut succeeded = 0u16;
+    let mut failed = 0u16;
+    let mut remaining = ctx.remaining_accounts.iter();
+    for (index, entry) in entries.into_iter().enumerate() {
+        let product_uuid = entry.product_uuid;
+        // Counted above, so every entry has its accounts
+        let product_info = remaining.next().ok_or(CustomError::InvalidParameters)?;
+        let sku_info = match entry.barcode {
+            Some(_) => Some(remaining.next().ok_or(CustomError::InvalidParameters)?),
+            None => None,
+        };
+        match create_product(
+            &store_key,
+            &authority,
+            &system_program_info,
+            product_info,
+            sku_info,
+            entry,
+        )
+        .and_then(|()| {
+            accounts
+                .catalog_index
+                .push(&mut accounts.catalog_page, product_info.key())
+        }) {
+            Ok(()) => succeeded += 1,
+            Err(err) => {
+                failed += 1;
+                emit!(ProductBatchItemFailed {
+                    store: store_key,
+                    index: index as u16,
+                    product_uuid,
+                    error_code: error_code(&err),
+                });
+            }
+        }
+    }
+
+    finish_batch(
+        &mut accounts.audit_log,
+        store_key,
+        authority.key(),
+        AuditAction::ProductsBatchRegistered,
+        succeeded,
+        failed,
+    )
+}
+
+/// Applies several product updates in one transaction, with the product
+/// accounts in `remaining_accounts` in entry order. Failing entries are
+/// skipped and reported through `ProductBatchItemFailed`.
+pub fn batch_update_products<'info>(
+    ctx: Context<'_, '_, 'info, 'info, BatchUpdateProducts<'info>>,
+    updates: Vec<ProductBatchUpdate>,
+) -> Result<()> {
+    require!(
+        !updates.is_empty() && updates.len() <= Product::MAX_BATCH_SIZE,
+        CustomError::InvalidParameters
+    );
+    require!(
+        ctx.remaining_accounts.len() == updates.len(),
+        CustomError::InvalidParameters
+    );
+
+    let store_key = ctx.accounts.store.key();
+    let authority = ctx.accounts.authority.to_account_info();
+    let system_program_info = ctx.accounts.system_program.to_account_info();
+
+    let mut succeeded = 0u16;
+    let mut failed = 0u16;
+    for (index, (update, product_info)) in updates
+        .into_iter()
+        .zip(ctx.remaining_accounts.iter())
+        .enumerate()
+    {
+        let product_uuid = update.product_uuid;
+        match apply_product_update(
+            &store_key,
+            &authority,
+            &system_program_info,
+            product_info,
+            update,
+        ) {
+            Ok(()) => succeeded += 1,
+            Err(err) => {
+                failed += 1;
+                emit!(ProductBatchItemFailed {
+                    store: store_key,
+                    index: index as u16,
+                    product_uuid,
+                    error_code: error_code(&err),
+                });
+            }
+        }
+    }
+
+    finish_batch(
+        &mut ctx.accounts.audit_log,
+        store_key,
+        authority.key(),
+        AuditAction::ProductsBatchUpdated,
+        succeeded,
+        failed,
+    )
+}
+
+// Everything that can fail is checked before the account is created, since a
+// failed CPI would abort the whole batch
+fn create_product<'info>(
+    store: &Pubkey,
+    authority: &AccountInfo<'info>,
+    system_program_info: &AccountInfo<'info>,
+    product_info: &AccountInfo<'info>,
+    sku_info: Option<&AccountInfo<'info>>,
+    entry: ProductBatchEntry,
+) -> Result<()> {
+    validate_metadata_uri(&entry.metadata_uri)?;
+    validate_attributes(&entry.attributes)?;
+
+    let (address, bump) = Pubkey::find_program_address(
+        &[b"product", store.as_ref(), entry.product_uuid.as_ref()],
+        &crate::ID,
+    );
+    require_keys_eq!(product_info.key(), address, CustomError::InvalidParameters);
+    require!(
+        is_unallocated(product_info),
+        CustomError::ProductAlreadyExists
+    );
+
+    // Check the barcode and its entry before creating anything, so a failing
+    // entry leaves no accounts behind
+    let sku = match (sku_info, entry.barcode.as_deref()) {
+        (None, None) => None,
+        (Some(sku_info), Some(barcode)) => {
+            require!(!barcode.is_empty(), CustomError::InvalidBarcode);
+            let (sku_address, sku_bump) = Pubkey::find_program_address(
+                &[b"sku", store.as_ref(), SkuEntry::seed(Some(barcode))?],
+                &crate::ID,
+            );
+            require_keys_eq!(sku_info.key(), sku_address, CustomError::InvalidBarcode);
+            require!(is_unallocated(sku_info), CustomError::InvalidBarcode);
+            Some((sku_info, sku_bump, barcode))
+        }
+        _ => return err!(CustomError::InvalidBarcode),
+    };
+
+    create_program_account(
+        authority,
+        system_program_info,
+        product_info,
+        &[
+            b"product",
+            store.as_ref(),
+            entry.product_uuid.as_ref(),
+            &[bump],
+        ],
+        Product::space(&entry.attributes),
+    )?;
+
+    if let Some((sku_info, sku_bump, barcode)) = sku {
+        create_program_account(
+            authority,
+            system_program_info,
+            sku_info,
+            &[b"sku", store.as_ref(), barcode.as_bytes(), &[sku_bump]],
+            SkuEntry::LEN,
+        )?;
+        let sku_entry = SkuEntry {
+            store: *store,
+            barcode: barcode.to_string(),
+            product_uuid: entry.product_uuid,
+            bump: sku_bump,
+        };
+        let mut data = sku_info.try_borrow_mut_data()?;
+        sku_entry.try_serialize(&mut &mut data[..])?;
+    }
+
+    let product = Product {
+        uuid: entry.product_uuid,
+        price: entry.price,
+        stock: entry.stock,
+        tokenized_type: entry.tokenized_type,
+        is_active: true,
+        metadata_uri: entry.metadata_uri,
+        store: *store,
+        authority: authority.key(),
+        attributes: entry.attributes,
+        barcode: entry.barcode,
+        variant_count: 0,
+    };
+    let mut data = product_info.try_borrow_mut_data()?;
+    product.try_serialize(&mut &mut data[..])
+}
+
+// Whether a PDA is still free to create: lamports may have been sent to it,
+// but nothing has allocated or claimed it
+fn is_unallocated(info: &AccountInfo) -> bool {
+    info.owner == &system_program::ID && info.data_is_empty()
+}
+
+// Creates a program-owned PDA account funded by `payer`. An address that was
+// pre-funded is topped up to rent exemption, then allocated and assigned, as
+// Anchor's `init` does, so a stray transfer can't block the address.
+fn create_program_account<'info>(
+    payer: &AccountInfo<'info>,
+    system_program_info: &AccountInfo<'info>,
+    account: &AccountInfo<'info>,
+    seeds: &[&[u8]],
+    space: usize,
+) -> Result<()> {
+    let rent = Rent::get()?.minimum_balance(space);
+    let current_lamports = account.lamports();
+    if current_lamports == 0 {
+        return system_program::create_account(
+            CpiContext::new_with_signer(
+                system_program_info.clone(),
+                system_program::CreateAccount {
+                    from: payer.clone(),
+                    to: account.clone(),
+                },
+                &[seeds],
+            ),
+            rent,
+            space as u64,
+            &crate::ID,
+        );
+    }
+
+    let top_up = rent.max(1).saturating_sub(current_lamports);
+    if top_up > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                system_program_info.clone(),
+                system_program::Transfer {
+                    from: payer.clone(),
+                    to: account.clone(),
+                },
+            ),
+            top_up,
+        )?;
+    }
+    system_program::allocate(
+        CpiContext::new_with_signer(
+            system_program_info.clone(),
+            system_program::Allocate {
+                account_to_allocate: account.clone(),
+            },
+            &[seeds],
+        ),
+        space as u64,
+    )?;
+    system_program::assign(
+        CpiContext::new_with_signer(
+            system_program_info.clone(),
+            system_program::Assign {
+                account_to_assign: account.clone(),
+            },
+            &[seeds],
+        ),
+        &crate::ID,
+    )
+}
+
+fn apply_product_update<'info>(
+    store: &Pubkey,
+    authority: &AccountInfo<'info>,
+    system_program_info: &AccountInfo<'info>,
+    product_info: &'info AccountInfo<'info>,
+    update: ProductBatchUpdate,
+) -> Result<()> {
+    let mut product = Account::<Product>::try_from(product_info)?;
+    require!(
+        product.uuid == update.product_uuid,
+        CustomError::ProductNotFound
+    );
+    require!(product.store == *store, CustomError::InvalidStore);
+    if let Some(metadata_uri) = &update.new_metadata_uri {
+        validate_metadata_uri(metadata_uri)?;
+    }
+    if let Some(attributes) = &update.new_attributes {
+        validate_attributes(attributes)?;
+    }
+
+    if let Some(price) = update.new_price {
+        product.price = price;
+    }
+    if let Some(stock) = update.new_stock {
+        product.stock = stock;
+    }
+    if let Some(metadata_uri) = update.new_metadata_uri {
+        product.metadata_uri = metadata_uri;
+    }
+    if let Some(tokenized_type) = update.new_tokenized_type {
+        product.tokenized_type = tokenized_type;
+    }
+    if let Some(attributes) = update.new_attributes {
+        resize_account(
+            product_info,
+            authority,
+            system_program_info,
+            Product::space(&attributes),
+        )?;
+        product.attributes = attributes;
+    }
+
+    product.exit(&crate::ID)
+}
+
+// One audit entry per batch rather than per product, so a large import
+// doesn't flush the store's recent history out of the ring buffer
+fn finish_batch(
+    audit_log: &mut AuditLog,
+    store: Pubkey,
+    authority: Pubkey,
+    action: AuditAction,
+    succeeded: u16,
+    failed: u16,
+) -> Result<()> {
+    audit_log.record(authority, action, store)?;
+
+    emit!(ProductBatchProcessed {
+        store,
+        authority,
+        succeeded,
+        failed,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+    Ok(())
+}
+
+fn error_code(err: &Error) -> u32 {
+    match err {
+        Error::AnchorError(e) => e.error_code_number,
+        Error::ProgramError(e) => u64::from(e.program_error.clone()) as u32,
+    }
+}
+
+fn validate_metadata_uri(metadata_uri: &str) -> Result<()> {
+    require!(
+        metadata_uri.len() <= Product::MAX_METADATA_URI_LEN,
+        CustomError::InvalidParameters
+    );
+    Ok(())
+}
+
+/// Closes a product along with its barcode entry and every variant, so none
+/// of them is left behind to be revived if the UUID is registered again. All
+/// of the product's `ProductVariant` accounts must be passed in
+/// `remaining_accounts`.
+pub fn close_product<'info>(
+    ctx: Context<'_, '_, 'info, 'info, CloseProduct<'info>>,
+    _product_uuid: [u8; 16],
+    _page_index: u32,
+) -> Result<()> {
+    let accounts = &mut *ctx.accounts;
+    // The barcode entry is closed along with the product
+    require!(
+        accounts.sku_entry.is_some() == accounts.product.barcode.is_some(),
+        CustomError::InvalidBarcode
+    );
+    let product = accounts.product.key();
+
+    require!(
+        ctx.remaining_accounts.len() == usize::from(accounts.product.variant_count),
+        CustomError::InvalidParameters
+    );
+    for variant_info in ctx.remaining_accounts {
+        // A variant passed twice fails here, as the first close hands the
+        // account back to the system program
+        let variant = Account::<ProductVariant>::try_from(variant_info)?;
+        require!(variant.product == product, CustomError::ProductNotFound);
+        variant.close(accounts.authority.to_account_info())?;
+    }
+    accounts
+        .catalog_index
+        .remove(&mut accounts.catalog_page, &product)?;
+
+    accounts.audit_log.record(
+        accounts.authority.key(),
+        AuditAction::ProductClosed,
+        product,
+    )?;
+    Ok(())
+}
+
+/// Points the product at a new barcode, or clears it with `None`. The old
+/// barcode's entry is closed and a new one is created.
+pub fn set_product_barcode(
+    ctx: Context<SetProductBarcode>,
+    product_uuid: [u8; 16],
+    barcode: Option<String>,
+) -> Result<()> {
+    let accounts = &mut *ctx.accounts;
+    require!(
+        accounts.old_sku_entry.is_some() == accounts.product.barcode.is_some(),
+        CustomError::InvalidBarcode
+    );
+    register_barcode(
+        accounts.new_sku_entry.as_deref_mut(),
+        ctx.bumps.new_sku_entry,
+        accounts.store.key(),
+        product_uuid,
+        barcode.as_deref(),
+    )?;
+    accounts.product.barcode = barcode;
+
+    accounts.audit_log.record(
+        accounts.authority.key(),
+        AuditAction::ProductBarcodeSet,
+        accounts.product.key(),
+    )?;
+    Ok(())
+}
+
+// Fills in a freshly created `SkuEntry`; the entry must be passed exactly
+// when a barcode is given
+fn register_barcode(
+    entry: Option<&mut SkuEntry>,
+    bump: Option<u8>,
+    store: Pubkey,
+    product_uuid: [u8; 16],
+    barcode: Option<&str>,
+) -> Result<()> {
+    match (entry, barcode) {
+        (None, None) => Ok(()),
+        (Some(entry), Some(barcode)) => {
+            require!(
+                !barcode.is_empty() && barcode.len() <= SkuEntry::MAX_BARCODE_LEN,
+                CustomError::InvalidBarcode
+            );
+            entry.store = store;
+            entry.barcode = barcode.to_string();
+            entry.product_uuid = product_uuid;
+            entry.bump = bump.ok_or(CustomError::InvalidBarcode)?;
+            Ok(())
+        }
+        _ => err!(CustomError::InvalidBarcode),
+    }
+}
+
+// Fills in the catalog index and page when `init_if_needed` just created them
+fn open_catalog_page(
+    index: &mut CatalogIndex,
+    index_bump: u8,
+    page: &mut CatalogPage,
+    page_bump: u8,
+    store: Pubkey,
+    count: usize,
+) {
+    if index.store == Pubkey::default() {
+        index.store = store;
+        index.bump = index_bump;
+    }
+    if page.store == Pubkey::default() {
+        page.store = store;
+        page.page_index = index.append_page(count);
+        page.bump = page_bump;
+    }
+}
+
+fn validate_attributes(attributes: &[ProductAttribute]) -> Result<()> {
+    require!(
+        attributes.len() <= Product::MAX_ATTRIBUTES
+            && attributes.iter().all(|a| {
+                !a.name.is_empty()
+                    && a.name.len() <= Product::MAX_ATTRIBUTE_NAME_LEN
+                    && a.value.len() <= Product::MAX_ATTRIBUTE_VALUE_LEN
+            }),
+        CustomError::InvalidProductAttributes
+    );
+    Ok(())
+}
+
+/// Instruction to add a variant (size, color, ...) under an existing product
+pub fn register_variant(
+    ctx: Context<RegisterVariant>,
+    _product_uuid: [u8; 16],
+    variant_id: [u8; 16],
+    sku: String,
+    options: Vec<ProductAttribute>,
+    price: u64,
+    stock: u64,
+) -> Result<()> {
+    require!(
+        sku.len() <= ProductVariant::MAX_SKU_LEN,
+        CustomError::InvalidParameters
+    );
+    require!(
+        options.len() <= ProductVariant::MAX_OPTIONS
+            && options.iter().all(|o| {
+                o.name.len() <= ProductVariant::MAX_OPTION_LEN
+                    && o.value.len() <= ProductVariant::MAX_OPTION_LEN
+            }),
+        CustomError::InvalidParameters
+    );
+
+    let variant = &mut ctx.accounts.variant;
+    variant.product = ctx.accounts.product.key();
+    variant.store = ctx.accounts.store.key();
+    variant.variant_id = variant_id;
+    variant.sku = sku;
+    variant.options = options;
+    variant.price = price;
+    variant.stock = stock;
+    variant.is_active = true;
+    variant.bump = ctx.bumps.variant;
+
+    let product = &mut ctx.accounts.product;
+    product.variant_count = product
+        .variant_count
+        .checked_add(1)
+        .ok_or(CustomError::InvalidParameters)?;
+
+    ctx.accounts.audit_log.record(
+        ctx.accounts.authority.key(),
+        AuditAction::VariantRegistered,
+        variant.key(),
+    )?;
+
+    Ok(())
+}
+
+pub fn update_variant(
+    ctx: Context<UpdateVariant>,
+    _product_uuid: [u8; 16],
+    _variant_id: [u8; 16],
+    new_price: Option<u64>,
+    new_stock: Option<u64>,
+    is_active: Option<bool>,
+) -> Result<()> {
+    let store = &ctx.accounts.store;
+    let authority = ctx.accounts.authority.key();
+
+    // Same split as products: catalog changes vs stock adjustments
+    if new_price.is_some() || is_active.is_some() {
+        require!(
+            store.has_permission(&authority, StorePermission::ManageProducts),
+            CustomError::Unauthorized
+        );
+    }
+    if new_stock.is_some() {
+        authorize_staff_or_session(
+            store,
+            &authority,
+            ctx.accounts.pos_session.as_mut(),
+            StorePermission::AdjustStock,
+            0,
+        )?;
+    }
+
+    let variant = &mut ctx.accounts.variant;
+    if let Some(price) = new_price {
+        variant.price = price;
+    }
+    if let Some(stock) = new_stock {
+        variant.stock = stock;
+    }
+    if let Some(is_active) = is_active {
+        variant.is_active = is_active;
+    }
+
+    ctx.accounts
+        .audit_log
+        .record(authority, AuditAction::VariantUpdated, variant.key())?;
+
+    Ok(())
+}
+
+/// Variant named by cart line `i`, if any; an empty `variant_ids` means none
+fn line_variant(variant_ids: &[Option<[u8; 16]>], i: usize) -> Option<[u8; 16]> {
+    variant_ids.get(i).copied().flatten()
+}
+
+/// Validate product cart items against remaining accounts and calculate total.
+/// Each line takes its `Product` account, followed by its `ProductVariant`
+/// account when the line names a variant. Also returns, per line, the index of
+/// the remaining account whose stock the line draws down.
 fn validate_cart_and_payment<'a, 'b>(
+    store: &Pubkey,
     product_uuids: &'a [[u8; 16]],
+    variant_ids: &'a [Option<[u8; 16]>],
     quantities: &'a [u64],
     remaining_accounts: &'b [AccountInfo<'b>],
-    total_amount_paid: u64,
-) -> Result<u64> {
+    max_amount: u64,
+    tip: u64,
+) -> Result<(u64, Vec<usize>)> {
     require!(
         product_uuids.len() == quantities.len() && !product_uuids.is_empty(),
         CustomError::InvalidCart
     );
+    require!(
+        variant_ids.is_empty() || variant_ids.len() == product_uuids.len(),
+        CustomError::InvalidCart
+    );
+    require!(
+        product_uuids.len() <= Purchase::MAX_ITEMS,
+        CustomError::CartTooLarge
+    );
 
     let mut total_price = 0u64;
+    let mut stock_accounts = Vec::with_capacity(product_uuids.len());
+    let mut cursor = 0;
     let mut i = 0;
     while i < product_uuids.len() {
-        let acc_info = &remaining_accounts[i];
-        let product = Account::<Product>::try_from(acc_info)?;
+        let product_info = remaining_accounts
+            .get(cursor)
+            .ok_or(CustomError::InvalidCart)?;
+        let product = Account::<Product>::try_from(product_info)?;
         require!(
             product.uuid == product_uuids[i],
             CustomError::ProductNotFound
         );
+        require!(product.store == *store, CustomError::InvalidStore);
         require!(product.is_active, CustomError::ProductNotFound);
-        require!(
-            product.stock >= quantities[i],
-            CustomError::InsufficientStock
-        );
+        require!(quantities[i] > 0, CustomError::InvalidCart);
+
+        // Price and stock come from the variant when the line names one
+        let (price, stock) = match line_variant(variant_ids, i) {
+            Some(variant_id) => {
+                cursor += 1;
+                let variant_info = remaining_accounts
+                    .get(cursor)
+                    .ok_or(CustomError::InvalidCart)?;
+                let variant = Account::<ProductVariant>::try_from(variant_info)?;
+                require!(
+                    variant.variant_id == variant_id && variant.product == product_info.key(),
+                    CustomError::ProductNotFound
+                );
+                require!(variant.is_active, CustomError::ProductNotFound);
+                (variant.price, variant.stock)
+            }
+            None => (product.price, product.stock),
+        };
+        require!(stock >= quantities[i], CustomError::InsufficientStock);
+        stock_accounts.push(cursor);
+        cursor += 1;
 
         // Calculate price for this item
-        let item_total = product
-            .price
+        let item_total = price
             .checked_mul(quantities[i])
-            .ok_or(CustomError::ArithmeticError)?;
+            .ok_or(CustomError::PriceOverflow)?;
         total_price = total_price
             .checked_add(item_total)
-            .ok_or(CustomError::ArithmeticError)?;
+            .ok_or(CustomError::PriceOverflow)?;
 
         i += 1;
     }
 
-    // Verify payment amount matches cart total
-    require!(
-        total_amount_paid >= total_price,
-        CustomError::InsufficientPayment
-    );
+    // The buyer authorizes at most `max_amount`; the cart total plus any
+    // explicit tip must fit within it
+    let amount_due = total_price
+        .checked_add(tip)
+        .ok_or(CustomError::PriceOverflow)?;
+    require!(amount_due <= max_amount, CustomError::MaxAmountExceeded);
 
-    Ok(total_price)
+    Ok((total_price, stock_accounts))
 }
 
 pub fn purchase_cart<'info>(
-    ctx: Context<'_, '_, 'info, 'info, PurchaseCart>,
+    ctx: Context<'_, '_, 'info, 'info, PurchaseCart<'info>>,
     product_uuids: Vec<[u8; 16]>,
+    variant_ids: Vec<Option<[u8; 16]>>,
     quantities: Vec<u64>,
-    total_amount_paid: u64,
+    max_amount: u64,
+    tip: u64,
     gas_fee: u64,
-    status: TransactionStatus,
 ) -> Result<()> {
     let remaining_accounts: &'info [AccountInfo<'info>] = ctx.remaining_accounts;
+    let store_key = ctx.accounts.store.key();
 
-    // Validate cart and get total price
-    let total_price = validate_cart_and_payment(
+    // Validate cart against on-chain prices and stock, and get total price
+    let (total_price, stock_accounts) = validate_cart_and_payment(
+        &store_key,
         &product_uuids,
+        &variant_ids,
         &quantities,
         remaining_accounts,
-        total_amount_paid,
+        max_amount,
+        tip,
     )?;
+    let amount_charged = total_price
+        .checked_add(tip)
+        .ok_or(CustomError::ArithmeticError)?;
 
-    // Transfer payment from buyer to escrow account
-    let escrow_seeds = &[
-        b"escrow",
-        ctx.accounts.store.to_account_info().key.as_ref(),
-        &[ctx.bumps.escrow_account],
-    ];
-    let signer_seeds = &[&escrow_seeds[..]];
-
+    // Transfer exactly the cart total plus tip from buyer to escrow account
     let transfer_to_escrow = CpiContext::new(
         ctx.accounts.system_program.to_account_info(),
         system_program::Transfer {
@@ -168,100 +882,166 @@ pub fn purchase_cart<'info>(
             to: ctx.accounts.escrow_account.to_account_info(),
         },
     );
-    system_program::transfer(transfer_to_escrow, total_price)?;
+    system_program::transfer(transfer_to_escrow, amount_charged)?;
 
     // Update escrow balance
     let escrow = &mut ctx.accounts.escrow_account;
+    escrow.store = store_key;
+    escrow.bump = ctx.bumps.escrow_account;
     escrow.balance = escrow
         .balance
-        .checked_add(total_price)
+        .checked_add(amount_charged)
         .ok_or(CustomError::ArithmeticError)?;
 
-    // Update product stocks
+    // Update product and variant stocks; the accounts are loaded outside the
+    // context so they have to be written back explicitly
     let mut i = 0;
-    while i < product_uuids.len() {
-        let product_account = &mut Account::<Product>::try_from(&remaining_accounts[i])?;
-        product_account.stock = product_account
-            .stock
-            .checked_sub(quantities[i])
-            .ok_or(CustomError::StockUnderflow)?;
+    while i < stock_accounts.len() {
+        let stock_info = &remaining_accounts[stock_accounts[i]];
+        require!(stock_info.is_writable, CustomError::InvalidCart);
+        if line_variant(&variant_ids, i).is_some() {
+            let mut variant = Account::<ProductVariant>::try_from(stock_info)?;
+            variant.stock = variant
+                .stock
+                .checked_sub(quantities[i])
+                .ok_or(CustomError::StockUnderflow)?;
+            variant.exit(&crate::ID)?;
+        } else {
+            let mut product_account = Account::<Product>::try_from(stock_info)?;
+            product_account.stock = product_account
+                .stock
+                .checked_sub(quantities[i])
+                .ok_or(CustomError::StockUnderflow)?;
+            product_account.exit(&crate::ID)?;
+        }
         i += 1;
     }
 
+    // Advance the buyer's order counter past the receipt created for this order
+    let counter = &mut ctx.accounts.order_counter;
+    let order_id = counter.order_count;
+    counter.store = store_key;
+    counter.buyer = ctx.accounts.buyer.key();
+    counter.order_count = order_id
+        .checked_add(1)
+        .ok_or(CustomError::ArithmeticError)?;
+
     // Create receipt
     let receipt = &mut ctx.accounts.receipt;
     receipt.product_uuids = product_uuids.clone();
+    receipt.variant_ids = variant_ids.clone();
     receipt.quantities = quantities.clone();
     receipt.total_paid = total_price;
     receipt.gas_fee = gas_fee;
-    receipt.status = status;
-    receipt.store = ctx.accounts.store.key();
+    receipt.tip = tip;
+    receipt.status = TransactionStatus::Completed;
+    receipt.anomaly = AnomalyFlag::None;
+    receipt.store = store_key;
     receipt.buyer = ctx.accounts.buyer.key();
     receipt.ts = Clock::get()?.unix_timestamp;
+    receipt.order_id = order_id;
+    let timestamp = receipt.ts;
 
-    emit!(CartPurchased {
-        store_id: ctx.accounts.store.key(),
-        buyer_id: ctx.accounts.buyer.key(),
-        product_uuids,
-        quantities,
-        total_paid: total_price,
-        gas_fee,
-        timestamp: receipt.ts,
-    });
-
-    Ok(())
-}
-
-pub fn release_escrow(ctx: Context<ReleaseEscrow>, amount: u64) -> Result<()> {
-    let escrow_info = ctx.accounts.escrow_account.to_account_info();
-    let escrow = &mut ctx.accounts.escrow_account;
-
-    let cpi_accounts = Transfer {
-        from: escrow_info,
-        to: ctx.accounts.store_owner.to_account_info(),
-    };
-
-    let cpi_program = ctx.accounts.system_program.to_account_info();
-    let store_key = ctx.accounts.store.key();
-    let seeds = &[
-        b"escrow".as_ref(),
-        store_key.as_ref(),
-        &[ctx.bumps.escrow_account],
-    ];
-    let signer_seeds = &[&seeds[..]];
-
-    let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
+    // Mint loyalty points if the store has loyalty enabled and all accounts were passed
+    let loyalty_points_earned = if let (
+        Some(loyalty_mint_info),
+        Some(token_mint),
+        Some(token_account),
+        Some(token_program),
+    ) = (
+        ctx.accounts.loyalty_mint_info.as_mut(),
+        ctx.accounts.token_mint.as_ref(),
+        ctx.accounts.token_account.as_ref(),
+        ctx.accounts.token_program.as_ref(),
+    ) {
+        require!(
+            token_mint.key() == loyalty_mint_info.mint,
+            CustomError::InvalidMint
+        );
 
-    system_program::transfer(cpi_ctx, amount)?;
+        // Calculate SOL amount (convert lamports to SOL)
+        let purchase_amount_sol = total_price
+            .checked_div(1_000_000_000)
+            .ok_or(CustomError::ArithmeticError)?;
 
-    escrow.balance = escrow.balance.checked_sub(amount).unwrap();
+        // Calculate points earned
+        let points = loyalty_mint_info
+            .points_per_sol
+            .checked_mul(purchase_amount_sol)
+            .ok_or(CustomError::ArithmeticError)?
+            .checked_mul(1_000_000) // Adjust for 6 decimal places in the token
+            .ok_or(CustomError::ArithmeticError)?;
 
-    Ok(())
-}
+        if points > 0 {
+            // The loyalty mint PDA is the SPL mint authority
+            let seeds = &[
+                b"loyalty_mint".as_ref(),
+                store_key.as_ref(),
+                &[loyalty_mint_info.bump],
+            ];
+            let signer_seeds = &[&seeds[..]];
+            let mint_accounts = token::MintTo {
+                mint: token_mint.to_account_info(),
+                to: token_account.to_account_info(),
+                authority: loyalty_mint_info.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new_with_signer(
+                token_program.to_account_info(),
+                mint_accounts,
+                signer_seeds,
+            );
+            token::mint_to(cpi_ctx, points)?;
 
-pub fn refund_from_escrow(ctx: Context<RefundEscrow>, amount: u64) -> Result<()> {
-    let escrow_info = ctx.accounts.escrow_account.to_account_info();
-    let escrow = &mut ctx.accounts.escrow_account;
+            loyalty_mint_info.total_points_issued = loyalty_mint_info
+                .total_points_issued
+                .checked_add(points)
+                .ok_or(CustomError::ArithmeticError)?;
 
-    let cpi_accounts = Transfer {
-        from: escrow_info,
-        to: ctx.accounts.buyer.to_account_info(),
+            emit!(LoyaltyPointsEarned {
+                user: ctx.accounts.buyer.key(),
+                store_id: store_key,
+                points_earned: points,
+                total_points: token_account.amount.saturating_add(points),
+                timestamp,
+            });
+        }
+        points
+    } else {
+        0
     };
+    ctx.accounts.receipt.loyalty_credited = loyalty_points_earned > 0;
 
-    let cpi_program = ctx.accounts.system_program.to_account_info();
-    let store_key = ctx.accounts.store.key();
-    let seeds = &[
-        b"escrow".as_ref(),
-        store_key.as_ref(),
-        &[ctx.bumps.escrow_account],
-    ];
-    let signer_seeds = &[&seeds[..]];
-
-    let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
-
-    system_program::transfer(cpi_ctx, amount)?;
+    emit!(CartPurchased {
+        store_id: store_key,
+        buyer_id: ctx.accounts.buyer.key(),
+        product_uuids,
+        variant_ids,
+        quantities,
+        total_paid: total_price,
+        gas_fee,
+        tip,
+        timestamp,
+        order_id,
+    });
+    emit!(PurchaseCompleted {
+        store: store_key,
+        buyer: ctx.accounts.buyer.key(),
+        total_amount: total_price,
+        timestamp,
+        loyalty_points_earned,
+    });
 
-    escrow.balance = escrow.balance.checked_sub(amount).unwrap();
+    msg!("Purchase completed - Total paid: {}", total_price);
+    if tip > 0 {
+        msg!("Tip: {}", tip);
+    }
+    msg!(
+        "Funds held in escrow: {}",
+        ctx.accounts.escrow_account.balance
+    );
+    if loyalty_points_earned > 0 {
+        msg!("Loyalty points earned: {}", loyalty_points_earned);
+    }
 
     Ok(())
 }
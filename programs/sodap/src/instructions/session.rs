@@ -0,0 +1,77 @@
+use crate::error::CustomError;
+use crate::state::audit::AuditAction;
+pub use crate::state::session::{
+    CreatePosSession, PosSession, PosSessionCreated, PosSessionRevoked, RevokePosSession,
+};
+use anchor_lang::prelude::*;
+
+/// Instruction for the store owner to register a POS terminal's session key
+pub fn create_pos_session(
+    ctx: Context<CreatePosSession>,
+    session_key: Pubkey,
+    label: String,
+    permissions: u16,
+    daily_cap: u64,
+    expires_at: i64,
+) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    require!(
+        label.len() <= PosSession::MAX_LABEL_LEN,
+        CustomError::InvalidParameters
+    );
+    require!(
+        permissions != 0 && permissions & !PosSession::ALLOWED_PERMISSIONS == 0,
+        CustomError::InvalidParameters
+    );
+    require!(
+        expires_at > now && expires_at - now <= PosSession::MAX_DURATION,
+        CustomError::InvalidParameters
+    );
+
+    let store_key = ctx.accounts.store.key();
+    let epoch = ctx.accounts.store.session_epoch;
+    let session = &mut ctx.accounts.pos_session;
+    session.store = store_key;
+    session.session_key = session_key;
+    session.label = label.clone();
+    session.permissions = permissions;
+    session.daily_cap = daily_cap;
+    session.spent_today = 0;
+    session.current_day = now / 86_400;
+    session.expires_at = expires_at;
+    session.created_by = ctx.accounts.owner.key();
+    session.epoch = epoch;
+    session.bump = ctx.bumps.pos_session;
+
+    ctx.accounts.audit_log.record(
+        ctx.accounts.owner.key(),
+        AuditAction::PosSessionCreated,
+        session_key,
+    )?;
+    emit!(PosSessionCreated {
+        store: store_key,
+        session_key,
+        label,
+        permissions,
+        daily_cap,
+        expires_at,
+    });
+
+    Ok(())
+}
+
+/// Instruction for the store owner to revoke a session key before it expires
+pub fn revoke_pos_session(ctx: Context<RevokePosSession>, session_key: Pubkey) -> Result<()> {
+    ctx.accounts.audit_log.record(
+        ctx.accounts.owner.key(),
+        AuditAction::PosSessionRevoked,
+        session_key,
+    )?;
+    emit!(PosSessionRevoked {
+        store: ctx.accounts.store.key(),
+        session_key,
+        revoked_at: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
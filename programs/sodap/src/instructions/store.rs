@@ -1,13 +1,15 @@
 use crate::error::CustomError;
-use crate::state::store::AdminRole;
-use crate::types::AdminRoleType;
+use crate::state::audit::AuditAction;
+use crate::state::store::{AdminRole, GuardianSet, Store, StoreSuspension};
 use crate::types::LoyaltyConfig;
+use crate::types::{AdminRoleType, StorePermission, SuspensionReason};
+use crate::utils::resize_account;
 use anchor_lang::prelude::*;
 
-/// Instruction to register a new store
+/// Instruction to register a new store under an owner-chosen store id
 pub fn register_store(
     ctx: Context<RegisterStore>,
-    _store_id: Pubkey, // optional, for logging purposes
+    _store_id: Pubkey, // PDA seed, chosen by the owner
     name: String,
     description: String,
     logo_uri: String,
@@ -26,11 +28,83 @@ pub fn register_store(
     store.loyalty_config = loyalty_config;
     store.is_active = true;
     store.revenue = 0;
-    store.admin_roles = vec![];
+    store.suspension = None;
+    store.guardian_set = None;
+    store.session_epoch = 0;
+
+    // The owner is the first admin with the Owner role
+    store.admin_roles = vec![AdminRole {
+        admin_pubkey: authority.key(),
+        role_type: AdminRoleType::Owner,
+        permissions: AdminRoleType::Owner.default_permissions(),
+        expires_at: None,
+        granted_at: Clock::get()?.unix_timestamp,
+    }];
+
+    let audit_log = &mut ctx.accounts.audit_log;
+    audit_log.scope = store.key();
+    audit_log.bump = ctx.bumps.audit_log;
+
+    // Record the store in the owner's index
+    let store_key = store.key();
+    add_to_owner_index(
+        &mut ctx.accounts.owner_index,
+        &authority.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        store_key,
+    )?;
+
+    emit!(StoreRegistered {
+        store_id: store_key,
+        owner: authority.key(),
+        name: store.name.clone(),
+        created_at: Clock::get()?.unix_timestamp,
+    });
 
     Ok(())
 }
 
+fn add_to_owner_index<'info>(
+    index: &mut Account<'info, OwnerStoreIndex>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    store: Pubkey,
+) -> Result<()> {
+    index.owner = payer.key();
+    if index.stores.contains(&store) {
+        return Ok(());
+    }
+    resize_account(
+        &index.to_account_info(),
+        payer,
+        system_program,
+        OwnerStoreIndex::space(index.stores.len() + 1),
+    )?;
+    index.stores.push(store);
+    Ok(())
+}
+
+// Takes the raw index account, which is skipped when it was never created
+fn remove_from_owner_index<'info>(
+    index_info: &AccountInfo<'info>,
+    rent_receiver: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    store: Pubkey,
+) -> Result<()> {
+    if index_info.owner != &crate::ID || index_info.data_is_empty() {
+        return Ok(());
+    }
+    let mut index = OwnerStoreIndex::try_deserialize(&mut &index_info.try_borrow_data()?[..])?;
+    index.stores.retain(|s| *s != store);
+    resize_account(
+        index_info,
+        rent_receiver,
+        system_program,
+        OwnerStoreIndex::space(index.stores.len()),
+    )?;
+    index.try_serialize(&mut &mut index_info.try_borrow_mut_data()?[..])
+}
+
 /// Instruction to update a store's metadata
 pub fn update_store(
     ctx: Context<UpdateStore>,
@@ -60,6 +134,428 @@ pub fn update_store(
         store.loyalty_config = config;
     }
 
+    ctx.accounts
+        .audit_log
+        .record(authority.key(), AuditAction::StoreUpdated, store.key())?;
+
+    emit!(StoreUpdated {
+        store_id: store.key(),
+        updated_by: authority.key(),
+        is_active: store.is_active,
+        updated_at: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+/// Instruction to pause or resume a store
+pub fn set_store_status(ctx: Context<SetStoreStatus>, is_active: bool) -> Result<()> {
+    let store = &mut ctx.accounts.store;
+    let authority = ctx.accounts.authority.key();
+
+    // Store owner or a platform admin can change the status
+    let is_platform_admin = ctx
+        .accounts
+        .platform_admin
+        .as_ref()
+        .is_some_and(|a| a.is_active());
+    require!(
+        authority == store.owner || is_platform_admin,
+        CustomError::Unauthorized
+    );
+
+    store.is_active = is_active;
+    ctx.accounts
+        .audit_log
+        .record(authority, AuditAction::StoreStatusChanged, store.key())?;
+
+    emit!(StoreUpdated {
+        store_id: store.key(),
+        updated_by: authority,
+        is_active,
+        updated_at: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+/// Instruction for a platform admin to suspend a store, freezing purchases and
+/// escrow releases while still allowing refunds
+pub fn suspend_store(
+    ctx: Context<ModerateStore>,
+    reason: SuspensionReason,
+    note: String,
+) -> Result<()> {
+    require!(
+        note.len() <= StoreSuspension::MAX_NOTE_LEN,
+        CustomError::InvalidParameters
+    );
+
+    let store = &mut ctx.accounts.store;
+    let authority = ctx.accounts.authority.key();
+    let now = Clock::get()?.unix_timestamp;
+
+    store.suspension = Some(StoreSuspension {
+        reason,
+        note: note.clone(),
+        suspended_by: authority,
+        suspended_at: now,
+    });
+    fit_store_account(
+        store,
+        &ctx.accounts.authority.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+    ctx.accounts
+        .audit_log
+        .record(authority, AuditAction::StoreSuspended, store.key())?;
+
+    emit!(StoreSuspended {
+        store_id: store.key(),
+        reason,
+        note,
+        suspended_by: authority,
+        suspended_at: now,
+    });
+
+    Ok(())
+}
+
+/// Instruction for a platform admin to lift a store suspension
+pub fn unsuspend_store(ctx: Context<ModerateStore>) -> Result<()> {
+    let store = &mut ctx.accounts.store;
+    require!(store.is_suspended(), CustomError::InvalidParameters);
+    store.suspension = None;
+    ctx.accounts.audit_log.record(
+        ctx.accounts.authority.key(),
+        AuditAction::StoreUnsuspended,
+        store.key(),
+    )?;
+
+    emit!(StoreUnsuspended {
+        store_id: store.key(),
+        unsuspended_by: ctx.accounts.authority.key(),
+        unsuspended_at: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+/// Instruction to propose handing the store to a new owner
+pub fn propose_ownership_transfer(
+    ctx: Context<ProposeOwnershipTransfer>,
+    new_owner: Pubkey,
+) -> Result<()> {
+    let store = &ctx.accounts.store;
+    require!(new_owner != store.owner, CustomError::InvalidParameters);
+
+    let transfer = &mut ctx.accounts.ownership_transfer;
+    transfer.store = store.key();
+    transfer.current_owner = store.owner;
+    transfer.new_owner = new_owner;
+    transfer.proposed_at = Clock::get()?.unix_timestamp;
+
+    ctx.accounts.audit_log.record(
+        store.owner,
+        AuditAction::OwnershipTransferProposed,
+        new_owner,
+    )?;
+
+    emit!(OwnershipTransferProposed {
+        store_id: store.key(),
+        current_owner: store.owner,
+        new_owner,
+        proposed_at: transfer.proposed_at,
+    });
+
+    Ok(())
+}
+
+/// Instruction for the proposed owner to accept a pending transfer
+pub fn accept_ownership_transfer(ctx: Context<AcceptOwnershipTransfer>) -> Result<()> {
+    let store = &mut ctx.accounts.store;
+    let previous_owner = store.owner;
+    let new_owner = ctx.accounts.new_owner.key();
+
+    // The seller's guardians must not be able to recover the store back; any
+    // pending recovery lapses with the change of owner
+    store.guardian_set = None;
+    hand_over_store(
+        store,
+        &ctx.accounts.new_owner.to_account_info(),
+        &ctx.accounts.previous_owner,
+        &ctx.accounts.previous_owner_index,
+        &mut ctx.accounts.new_owner_index,
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+
+    ctx.accounts.audit_log.record(
+        new_owner,
+        AuditAction::OwnershipTransferAccepted,
+        previous_owner,
+    )?;
+
+    emit!(OwnershipTransferred {
+        store_id: store.key(),
+        previous_owner,
+        new_owner,
+        transferred_at: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+/// Makes `new_owner` the store owner: moves the Owner role, resizes the store
+/// and moves the store between the two owners' indexes
+fn hand_over_store<'info>(
+    store: &mut Account<'info, Store>,
+    new_owner: &AccountInfo<'info>,
+    previous_owner: &AccountInfo<'info>,
+    previous_owner_index: &AccountInfo<'info>,
+    new_owner_index: &mut Account<'info, OwnerStoreIndex>,
+    system_program: &AccountInfo<'info>,
+) -> Result<()> {
+    let new_owner_key = new_owner.key();
+    let now = Clock::get()?.unix_timestamp;
+    store.owner = new_owner_key;
+
+    // The new owner starts with no staff and no live POS sessions; anything
+    // the previous owner granted has to be granted again
+    store.admin_roles = vec![AdminRole {
+        admin_pubkey: new_owner_key,
+        role_type: AdminRoleType::Owner,
+        permissions: AdminRoleType::Owner.default_permissions(),
+        expires_at: None,
+        granted_at: now,
+    }];
+    store.session_epoch = store.session_epoch.wrapping_add(1);
+    fit_store_account(store, new_owner, system_program)?;
+
+    // Move the store between the owners' indexes. The previous index is
+    // always passed so the new owner cannot leave the store listed there.
+    let store_key = store.key();
+    remove_from_owner_index(
+        previous_owner_index,
+        previous_owner,
+        system_program,
+        store_key,
+    )?;
+    add_to_owner_index(new_owner_index, new_owner, system_program, store_key)
+}
+
+/// Instruction for the owner to configure, replace or clear (`None`) the
+/// guardians who can recover the store
+pub fn set_guardians(ctx: Context<SetGuardians>, guardian_set: Option<GuardianSet>) -> Result<()> {
+    let store = &mut ctx.accounts.store;
+
+    if let Some(set) = &guardian_set {
+        let count = set.guardians.len();
+        require!(
+            count > 0 && count <= GuardianSet::MAX_GUARDIANS,
+            CustomError::InvalidParameters
+        );
+        require!(
+            set.threshold > 0 && set.threshold as usize <= count,
+            CustomError::InvalidParameters
+        );
+        require!(
+            set.recovery_delay >= GuardianSet::MIN_RECOVERY_DELAY,
+            CustomError::InvalidParameters
+        );
+        require!(
+            !set.guardians.contains(&store.owner),
+            CustomError::InvalidParameters
+        );
+        let mut unique = set.guardians.clone();
+        unique.sort();
+        unique.dedup();
+        require!(unique.len() == count, CustomError::InvalidParameters);
+    }
+
+    store.guardian_set = guardian_set.clone();
+    fit_store_account(
+        store,
+        &ctx.accounts.owner.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+
+    ctx.accounts.audit_log.record(
+        ctx.accounts.owner.key(),
+        AuditAction::GuardiansUpdated,
+        store.key(),
+    )?;
+    let (guardians, threshold, recovery_delay) = guardian_set.map_or((vec![], 0, 0), |s| {
+        (s.guardians, s.threshold, s.recovery_delay)
+    });
+    emit!(GuardiansUpdated {
+        store_id: store.key(),
+        guardians,
+        threshold,
+        recovery_delay,
+        updated_at: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+/// Instruction for a guardian to start recovering the store to `new_owner`
+pub fn initiate_owner_recovery(
+    ctx: Context<InitiateOwnerRecovery>,
+    new_owner: Pubkey,
+) -> Result<()> {
+    let store = &ctx.accounts.store;
+    let guardian = ctx.accounts.guardian.key();
+    let guardian_set = store
+        .guardian_set
+        .as_ref()
+        .ok_or(CustomError::Unauthorized)?;
+    require!(
+        guardian_set.guardians.contains(&guardian),
+        CustomError::Unauthorized
+    );
+    require!(new_owner != store.owner, CustomError::InvalidParameters);
+
+    let now = Clock::get()?.unix_timestamp;
+    let recovery = &mut ctx.accounts.owner_recovery;
+    recovery.store = store.key();
+    recovery.current_owner = store.owner;
+    recovery.new_owner = new_owner;
+    recovery.initiator = guardian;
+    recovery.approvals = vec![guardian];
+    recovery.initiated_at = now;
+    recovery.executable_at = now
+        .checked_add(guardian_set.recovery_delay)
+        .ok_or(CustomError::ArithmeticError)?;
+    recovery.bump = ctx.bumps.owner_recovery;
+
+    ctx.accounts
+        .audit_log
+        .record(guardian, AuditAction::OwnerRecoveryInitiated, new_owner)?;
+    emit!(OwnerRecoveryInitiated {
+        store_id: store.key(),
+        new_owner,
+        initiator: guardian,
+        executable_at: recovery.executable_at,
+    });
+
+    Ok(())
+}
+
+/// Instruction for another guardian to back a pending recovery
+pub fn approve_owner_recovery(ctx: Context<ApproveOwnerRecovery>) -> Result<()> {
+    let store = &ctx.accounts.store;
+    let guardian = ctx.accounts.guardian.key();
+    require!(
+        store
+            .guardian_set
+            .as_ref()
+            .is_some_and(|s| s.guardians.contains(&guardian)),
+        CustomError::Unauthorized
+    );
+
+    let recovery = &mut ctx.accounts.owner_recovery;
+    require!(
+        !recovery.approvals.contains(&guardian),
+        CustomError::AlreadyApproved
+    );
+    recovery.approvals.push(guardian);
+
+    ctx.accounts.audit_log.record(
+        guardian,
+        AuditAction::OwnerRecoveryApproved,
+        recovery.new_owner,
+    )?;
+    emit!(OwnerRecoveryApproved {
+        store_id: store.key(),
+        guardian,
+        approvals: recovery.approvals.len() as u8,
+    });
+
+    Ok(())
+}
+
+/// Instruction for the current owner to veto a pending recovery
+pub fn veto_owner_recovery(ctx: Context<VetoOwnerRecovery>) -> Result<()> {
+    let new_owner = ctx.accounts.owner_recovery.new_owner;
+
+    ctx.accounts.audit_log.record(
+        ctx.accounts.owner.key(),
+        AuditAction::OwnerRecoveryVetoed,
+        new_owner,
+    )?;
+    emit!(OwnerRecoveryVetoed {
+        store_id: ctx.accounts.store.key(),
+        new_owner,
+        vetoed_at: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+/// Instruction for the recovered key to take over the store once the timelock
+/// has passed with enough guardian approvals
+pub fn execute_owner_recovery(ctx: Context<ExecuteOwnerRecovery>) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    let recovery = &ctx.accounts.owner_recovery;
+    require!(
+        now >= recovery.executable_at,
+        CustomError::RecoveryTimelockActive
+    );
+
+    // Approvals are recounted against the current guardian set and threshold
+    let guardian_set = ctx
+        .accounts
+        .store
+        .guardian_set
+        .as_ref()
+        .ok_or(CustomError::Unauthorized)?;
+    require!(
+        recovery.valid_approvals(guardian_set) >= guardian_set.threshold as usize,
+        CustomError::InsufficientApprovals
+    );
+
+    let store = &mut ctx.accounts.store;
+    let previous_owner = store.owner;
+    let new_owner = ctx.accounts.new_owner.key();
+
+    hand_over_store(
+        store,
+        &ctx.accounts.new_owner.to_account_info(),
+        &ctx.accounts.previous_owner,
+        &ctx.accounts.previous_owner_index,
+        &mut ctx.accounts.new_owner_index,
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+
+    ctx.accounts.audit_log.record(
+        ctx.accounts.owner_recovery.key(),
+        AuditAction::OwnerRecoveryExecuted,
+        new_owner,
+    )?;
+    emit!(OwnershipTransferred {
+        store_id: store.key(),
+        previous_owner,
+        new_owner,
+        transferred_at: now,
+    });
+
+    Ok(())
+}
+
+/// Instruction for the current owner to withdraw a pending transfer
+pub fn cancel_ownership_transfer(ctx: Context<CancelOwnershipTransfer>) -> Result<()> {
+    ctx.accounts.audit_log.record(
+        ctx.accounts.owner.key(),
+        AuditAction::OwnershipTransferCancelled,
+        ctx.accounts.ownership_transfer.new_owner,
+    )?;
+
+    emit!(OwnershipTransferCancelled {
+        store_id: ctx.accounts.store.key(),
+        cancelled_by: ctx.accounts.owner.key(),
+        cancelled_at: Clock::get()?.unix_timestamp,
+    });
+
     Ok(())
 }
 
@@ -69,27 +565,61 @@ pub fn add_admin(
     _store_id: Pubkey,
     admin_pubkey: Pubkey,
     role_type: AdminRoleType,
+    permissions: Option<u16>,
+    expires_at: Option<i64>,
 ) -> Result<()> {
     let store = &mut ctx.accounts.store;
-    let authority = &ctx.accounts.owner;
+    let authority = &ctx.accounts.authority;
+    let now = Clock::get()?.unix_timestamp;
 
-    // Only the owner can add admins
-    require!(authority.key() == store.owner, CustomError::Unauthorized);
-    require!(authority.is_signer, CustomError::Unauthorized);
+    // Ownership is only handed over through the ownership transfer flow
+    require!(
+        role_type != AdminRoleType::Owner,
+        CustomError::InvalidAdminRole
+    );
+    let permissions = permissions.unwrap_or_else(|| role_type.default_permissions());
+    require!(
+        permissions & !StorePermission::ALL == 0,
+        CustomError::InvalidParameters
+    );
+    if let Some(expires_at) = expires_at {
+        require!(expires_at > now, CustomError::InvalidParameters);
+    }
+    require!(
+        can_delegate(store, &authority.key(), permissions, now),
+        CustomError::Unauthorized
+    );
 
-    if store
-        .admin_roles
-        .iter()
-        .any(|r| r.admin_pubkey == admin_pubkey)
-    {
+    if store.active_role(&admin_pubkey, now).is_some() {
         return Err(CustomError::AdminAlreadyExists.into());
     }
+    // A lapsed grant for the same wallet is replaced by the new one
+    store.admin_roles.retain(|r| r.admin_pubkey != admin_pubkey);
 
     store.admin_roles.push(AdminRole {
+        admin_pubkey,
+        role_type: role_type.clone(),
+        permissions,
+        expires_at,
+        granted_at: now,
+    });
+
+    ctx.accounts
+        .audit_log
+        .record(authority.key(), AuditAction::AdminAdded, admin_pubkey)?;
+    emit!(AdminAdded {
+        store_id: store.key(),
         admin_pubkey,
         role_type,
+        added_at: now,
     });
-    Ok(())
+
+    // Grow the account if the roster no longer fits; the caller pays the rent
+    fit_store_account(
+        store,
+        &authority.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+    )
 }
 
 /// Instruction to remove an admin from a store
@@ -99,15 +629,182 @@ pub fn remove_admin(
     admin_pubkey: Pubkey,
 ) -> Result<()> {
     let store = &mut ctx.accounts.store;
-    let authority = &ctx.accounts.owner;
+    let authority = &ctx.accounts.authority;
+    let now = Clock::get()?.unix_timestamp;
 
-    // Only the owner can remove admins
-    require!(authority.key() == store.owner, CustomError::Unauthorized);
-    require!(authority.is_signer, CustomError::Unauthorized);
+    let index = store
+        .admin_roles
+        .iter()
+        .position(|r| r.admin_pubkey == admin_pubkey)
+        .ok_or(CustomError::AdminNotFound)?;
+    let target = &store.admin_roles[index];
 
-    store.admin_roles.retain(|r| r.admin_pubkey != admin_pubkey);
+    // The owner only leaves through an ownership transfer or recovery
+    require!(
+        admin_pubkey != store.owner && target.role_type != AdminRoleType::Owner,
+        CustomError::CannotRemoveOwner
+    );
+    // Staff can only remove admins holding no more permissions than they do
+    require!(
+        can_delegate(store, &authority.key(), target.permissions, now),
+        CustomError::Unauthorized
+    );
+
+    store.admin_roles.remove(index);
+
+    ctx.accounts
+        .audit_log
+        .record(authority.key(), AuditAction::AdminRemoved, admin_pubkey)?;
+    emit!(AdminRemoved {
+        store_id: store.key(),
+        admin_pubkey,
+        removed_at: now,
+    });
+
+    // Shrink the account back towards its initial size and refund the owner
+    fit_store_account(
+        store,
+        &ctx.accounts.store_owner,
+        &ctx.accounts.system_program.to_account_info(),
+    )
+}
+
+/// Instruction to promote or demote an existing admin in place
+pub fn change_admin_role(
+    ctx: Context<ChangeAdminRole>,
+    _store_id: Pubkey,
+    admin_pubkey: Pubkey,
+    role_type: AdminRoleType,
+    permissions: Option<u16>,
+) -> Result<()> {
+    let store = &mut ctx.accounts.store;
+    let authority = ctx.accounts.authority.key();
+    let now = Clock::get()?.unix_timestamp;
+
+    require!(
+        role_type != AdminRoleType::Owner,
+        CustomError::InvalidAdminRole
+    );
+    let permissions = permissions.unwrap_or_else(|| role_type.default_permissions());
+    require!(
+        permissions & !StorePermission::ALL == 0,
+        CustomError::InvalidParameters
+    );
+
+    let index = store
+        .admin_roles
+        .iter()
+        .position(|r| r.admin_pubkey == admin_pubkey)
+        .ok_or(CustomError::AdminNotFound)?;
+    let previous = store.admin_roles[index].clone();
+    require!(
+        admin_pubkey != store.owner && previous.role_type != AdminRoleType::Owner,
+        CustomError::CannotRemoveOwner
+    );
+    // The caller must hold both what the admin had and what they're being given
+    require!(
+        can_delegate(store, &authority, previous.permissions | permissions, now),
+        CustomError::Unauthorized
+    );
+
+    let role = &mut store.admin_roles[index];
+    role.role_type = role_type.clone();
+    // Newly added permissions count as a fresh grant for escrow approvals
+    if permissions & !role.permissions != 0 {
+        role.granted_at = now;
+    }
+    role.permissions = permissions;
+
+    ctx.accounts
+        .audit_log
+        .record(authority, AuditAction::AdminRoleChanged, admin_pubkey)?;
+    emit!(AdminRoleChanged {
+        store_id: store.key(),
+        admin_pubkey,
+        previous_role: previous.role_type,
+        new_role: role_type,
+        permissions,
+        changed_by: authority,
+        changed_at: now,
+    });
+
+    Ok(())
+}
+
+/// Instruction to drop every expired staff grant from a store
+pub fn prune_expired_admins(ctx: Context<PruneExpiredAdmins>, _store_id: Pubkey) -> Result<()> {
+    let store = &mut ctx.accounts.store;
+    let now = Clock::get()?.unix_timestamp;
+    let store_key = store.key();
+
+    let (expired, active): (Vec<AdminRole>, Vec<AdminRole>) =
+        store.admin_roles.drain(..).partition(|r| r.is_expired(now));
+    store.admin_roles = active;
+
+    for role in &expired {
+        ctx.accounts.audit_log.record(
+            ctx.accounts.caller.key(),
+            AuditAction::AdminRemoved,
+            role.admin_pubkey,
+        )?;
+        emit!(AdminRemoved {
+            store_id: store_key,
+            admin_pubkey: role.admin_pubkey,
+            removed_at: now,
+        });
+    }
+    msg!("Pruned {} expired admin grants", expired.len());
+
+    fit_store_account(
+        store,
+        &ctx.accounts.store_owner,
+        &ctx.accounts.system_program.to_account_info(),
+    )
+}
+
+/// Whether `authority` may grant or revoke the given permission set; the owner
+/// may delegate anything, staff only what they hold themselves
+fn can_delegate(store: &Store, authority: &Pubkey, permissions: u16, now: i64) -> bool {
+    if *authority == store.owner {
+        return true;
+    }
+    store
+        .active_role(authority, now)
+        .is_some_and(|r| r.permissions & permissions == permissions)
+}
+
+/// Resize the store account to hold its current contents, never shrinking
+/// below the initial `Store::LEN` allocation
+fn fit_store_account<'info>(
+    store: &Account<'info, Store>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<()> {
+    let store_info = store.to_account_info();
+    let current = store_info.data_len();
+    let needed = store.required_space()?;
+
+    let new_len = if needed > current {
+        needed
+    } else if current > Store::LEN {
+        needed.max(Store::LEN)
+    } else {
+        current
+    };
+
+    if new_len != current {
+        resize_account(&store_info, payer, system_program, new_len)?;
+    }
     Ok(())
 }
 
 // Re-export contexts from state
-pub use crate::state::store::{AddAdmin, RegisterStore, RemoveAdmin, UpdateStore};
+pub use crate::state::store::{
+    AcceptOwnershipTransfer, AddAdmin, AdminAdded, AdminRemoved, AdminRoleChanged,
+    ApproveOwnerRecovery, CancelOwnershipTransfer, ChangeAdminRole, ExecuteOwnerRecovery,
+    GuardiansUpdated, InitiateOwnerRecovery, ModerateStore, OwnerRecoveryApproved,
+    OwnerRecoveryInitiated, OwnerRecoveryVetoed, OwnerStoreIndex, OwnershipTransferCancelled,
+    OwnershipTransferProposed, OwnershipTransferred, ProposeOwnershipTransfer, PruneExpiredAdmins,
+    RegisterStore, RemoveAdmin, SetGuardians, SetStoreStatus, StoreRegistered, StoreSuspended,
+    StoreUnsuspended, StoreUpdated, UpdateStore, VetoOwnerRecovery,
+};
@@ -1,9 +1,9 @@
 // User profile-related instructions, events, and accounts will be placed here.
 
 use crate::error::CustomError;
-use crate::state::user::UserProfileUpdated;
+use crate::state::product::SkuEntry;
 pub use crate::state::user::{CreateOrUpdateUserProfile, ScanAndPurchase};
-use crate::types::*;
+use crate::state::user::{ItemsScanned, UserProfileUpdated};
 use anchor_lang::prelude::*;
 
 pub fn create_or_update_user_profile(
@@ -45,16 +45,42 @@ pub fn create_or_update_user_profile(
     Ok(())
 }
 
-pub fn scan_and_purchase(
-    ctx: Context<ScanAndPurchase>,
-    product_uuids: Vec<[u8; 16]>,
+/// Items may be given by product UUID or by scanned barcode. Barcode lines
+/// come after the UUID lines in `quantities`, and each needs its `SkuEntry`
+/// in `remaining_accounts`, in the same order.
+pub fn scan_and_purchase<'info>(
+    ctx: Context<'_, '_, 'info, 'info, ScanAndPurchase<'info>>,
+    mut product_uuids: Vec<[u8; 16]>,
+    barcodes: Vec<String>,
     quantities: Vec<u64>,
     _store_id: Pubkey,
 ) -> Result<()> {
     require!(
-        product_uuids.len() == quantities.len(),
+        product_uuids.len() + barcodes.len() == quantities.len(),
         CustomError::InvalidCart
     );
+    require!(
+        ctx.remaining_accounts.len() == barcodes.len(),
+        CustomError::InvalidCart
+    );
+
+    let store = ctx.accounts.store.key();
+    for (barcode, entry_info) in barcodes.iter().zip(ctx.remaining_accounts.iter()) {
+        let entry = Account::<SkuEntry>::try_from(entry_info)?;
+        require!(
+            entry.store == store && entry.barcode == *barcode,
+            CustomError::InvalidBarcode
+        );
+        product_uuids.push(entry.product_uuid);
+    }
+    emit!(ItemsScanned {
+        buyer: ctx.accounts.authority.key(),
+        store,
+        product_uuids,
+        quantities,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
     let user_profile = &mut ctx.accounts.user_profile;
     user_profile.total_purchases += 1;
     emit!(UserProfileUpdated {
@@ -1,46 +1,205 @@
 // Admin and platform admin-related instructions, events, and accounts will be placed here.
 
-use crate::error;
-use crate::utils::{check_root_password, is_super_root_admin};
+use super::audit::AuditLog;
+use crate::error::CustomError;
+use crate::program::Sodap;
+use crate::types::PlatformAdminStatus;
 use anchor_lang::prelude::*;
 
-// Platform admin accounts
+// Singleton holding the platform super admin
 #[account]
-pub struct PlatformAdmins {
-    pub admins: Vec<Pubkey>,
+pub struct PlatformConfig {
+    pub super_admin: Pubkey,
+    pub bump: u8,
 }
 
-impl PlatformAdmins {
-    pub const LEN: usize = 4 + 32 * 10; // Up to 10 platform admins
+impl PlatformConfig {
+    pub const LEN: usize = 8 + 32 + 1;
+}
+
+// One account per platform admin, so the registry has no fixed cap
+#[account]
+pub struct PlatformAdmin {
+    pub admin_pubkey: Pubkey,
+    pub name: String,
+    pub added_by: Pubkey,
+    pub added_at: i64,
+    pub status: PlatformAdminStatus,
+    pub bump: u8,
+}
+
+impl PlatformAdmin {
+    pub const MAX_NAME_LEN: usize = 64;
+    pub const LEN: usize = 8 + 32 + (4 + Self::MAX_NAME_LEN) + 32 + 8 + 1 + 1;
+
+    pub fn is_active(&self) -> bool {
+        self.status == PlatformAdminStatus::Active
+    }
 }
 
 // Platform admin events
+#[event]
+pub struct PlatformInitialized {
+    pub super_admin: Pubkey,
+    pub initialized_at: i64,
+}
+
+#[event]
+pub struct SuperAdminRotated {
+    pub previous_super_admin: Pubkey,
+    pub new_super_admin: Pubkey,
+    pub rotated_at: i64,
+}
+
 #[event]
 pub struct PlatformAdminAdded {
     pub admin_pubkey: Pubkey,
+    pub name: String,
+    pub added_by: Pubkey,
     pub added_at: i64,
 }
 
+#[event]
+pub struct PlatformAdminStatusChanged {
+    pub admin_pubkey: Pubkey,
+    pub status: PlatformAdminStatus,
+    pub changed_by: Pubkey,
+    pub changed_at: i64,
+}
+
 #[event]
 pub struct PlatformAdminRemoved {
     pub admin_pubkey: Pubkey,
     pub removed_at: i64,
 }
 
+// Only the program's upgrade authority can create the platform config
+#[derive(Accounts)]
+pub struct InitializePlatform<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = PlatformConfig::LEN,
+        seeds = [b"platform_config"],
+        bump
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(
+        init,
+        payer = authority,
+        space = AuditLog::LEN,
+        seeds = [b"audit_log", platform_config.key().as_ref()],
+        bump
+    )]
+    pub audit_log: Account<'info, AuditLog>,
+    #[account(
+        constraint = program.programdata_address()? == Some(program_data.key()) @ CustomError::Unauthorized
+    )]
+    pub program: Program<'info, Sodap>,
+    #[account(
+        constraint = program_data.upgrade_authority_address == Some(authority.key()) @ CustomError::Unauthorized
+    )]
+    pub program_data: Account<'info, ProgramData>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+// Both the current and the incoming super admin must sign a rotation
 #[derive(Accounts)]
+pub struct RotateSuperAdmin<'info> {
+    #[account(
+        mut,
+        seeds = [b"platform_config"],
+        bump = platform_config.bump,
+        has_one = super_admin @ CustomError::Unauthorized
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(
+        mut,
+        seeds = [b"audit_log", platform_config.key().as_ref()],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Account<'info, AuditLog>,
+    pub super_admin: Signer<'info>,
+    pub new_super_admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(admin_pubkey: Pubkey)]
 pub struct AddPlatformAdmin<'info> {
-    #[account(mut, seeds = [b"platform_admins"], bump)]
-    pub platform_admins: Account<'info, PlatformAdmins>,
-    /// CHECK: This is safe because we only check that the signer is the super root admin
-    #[account(signer)]
-    pub signer: AccountInfo<'info>,
+    #[account(
+        seeds = [b"platform_config"],
+        bump = platform_config.bump,
+        has_one = super_admin @ CustomError::Unauthorized
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(
+        mut,
+        seeds = [b"audit_log", platform_config.key().as_ref()],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Account<'info, AuditLog>,
+    #[account(
+        init,
+        payer = super_admin,
+        space = PlatformAdmin::LEN,
+        seeds = [b"platform_admin", admin_pubkey.as_ref()],
+        bump
+    )]
+    pub platform_admin: Account<'info, PlatformAdmin>,
+    #[account(mut)]
+    pub super_admin: Signer<'info>,
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
+#[instruction(admin_pubkey: Pubkey)]
 pub struct RemovePlatformAdmin<'info> {
-    #[account(mut, seeds = [b"platform_admins"], bump)]
-    pub platform_admins: Account<'info, PlatformAdmins>,
-    /// CHECK: This is safe because we only check that the signer is the super root admin
-    #[account(signer)]
-    pub signer: AccountInfo<'info>,
+    #[account(
+        seeds = [b"platform_config"],
+        bump = platform_config.bump,
+        has_one = super_admin @ CustomError::Unauthorized
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(
+        mut,
+        seeds = [b"audit_log", platform_config.key().as_ref()],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Account<'info, AuditLog>,
+    #[account(
+        mut,
+        seeds = [b"platform_admin", admin_pubkey.as_ref()],
+        bump = platform_admin.bump,
+        close = super_admin
+    )]
+    pub platform_admin: Account<'info, PlatformAdmin>,
+    #[account(mut)]
+    pub super_admin: Signer<'info>,
+}
+
+// Suspend or reinstate a platform admin without deleting their record
+#[derive(Accounts)]
+#[instruction(admin_pubkey: Pubkey)]
+pub struct SetPlatformAdminStatus<'info> {
+    #[account(
+        seeds = [b"platform_config"],
+        bump = platform_config.bump,
+        has_one = super_admin @ CustomError::Unauthorized
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+    #[account(
+        mut,
+        seeds = [b"audit_log", platform_config.key().as_ref()],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Account<'info, AuditLog>,
+    #[account(
+        mut,
+        seeds = [b"platform_admin", admin_pubkey.as_ref()],
+        bump = platform_admin.bump
+    )]
+    pub platform_admin: Account<'info, PlatformAdmin>,
+    pub super_admin: Signer<'info>,
 }
@@ -0,0 +1,192 @@
+use super::store::Store;
+use anchor_lang::prelude::*;
+
+// Fixed-size ring buffer of privileged actions. One log exists per store and
+// one for the platform (seeded by the platform config); once full, the oldest
+// entry is overwritten and sits at `next_index`.
+#[account]
+pub struct AuditLog {
+    pub scope: Pubkey,
+    pub next_index: u32,
+    pub total_entries: u64,
+    pub entries: Vec<AuditEntry>,
+    pub bump: u8,
+}
+
+impl AuditLog {
+    pub const CAPACITY: usize = 32;
+    pub const LEN: usize = 8 + 32 + 4 + 8 + (4 + AuditEntry::LEN * Self::CAPACITY) + 1;
+
+    /// Appends an entry stamped with the current cluster time
+    pub fn record(&mut self, actor: Pubkey, action: AuditAction, target: Pubkey) -> Result<()> {
+        self.record_at(actor, action, target, Clock::get()?.unix_timestamp);
+        Ok(())
+    }
+
+    /// Appends an entry with an explicit timestamp, overwriting the oldest once full
+    pub fn record_at(
+        &mut self,
+        actor: Pubkey,
+        action: AuditAction,
+        target: Pubkey,
+        timestamp: i64,
+    ) {
+        let entry = AuditEntry {
+            actor,
+            action,
+            target,
+            timestamp,
+        };
+        let index = self.next_index as usize;
+        if index < self.entries.len() {
+            self.entries[index] = entry;
+        } else {
+            self.entries.push(entry);
+        }
+        self.next_index = ((index + 1) % Self::CAPACITY) as u32;
+        self.total_entries = self.total_entries.saturating_add(1);
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct AuditEntry {
+    /// Signer that authorized the action; the proposal account for executed escrow proposals
+    pub actor: Pubkey,
+    pub action: AuditAction,
+    /// Account or wallet the action applied to
+    pub target: Pubkey,
+    pub timestamp: i64,
+}
+
+impl AuditEntry {
+    pub const LEN: usize = 32 + 1 + 32 + 8;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq)]
+pub enum AuditAction {
+    StoreUpdated,
+    StoreStatusChanged,
+    StoreSuspended,
+    StoreUnsuspended,
+    OwnershipTransferProposed,
+    OwnershipTransferAccepted,
+    OwnershipTransferCancelled,
+    AdminAdded,
+    AdminRemoved,
+    ProductRegistered,
+    ProductUpdated,
+    ProductDeactivated,
+    EscrowReleased,
+    EscrowRefunded,
+    EscrowPolicyUpdated,
+    EscrowProposalCreated,
+    EscrowProposalApproved,
+    EscrowProposalExecuted,
+    EscrowProposalCancelled,
+    LoyaltyMintInitialized,
+    LoyaltyPointsMinted,
+    SuperAdminRotated,
+    PlatformAdminAdded,
+    PlatformAdminRemoved,
+    PlatformAdminStatusChanged,
+    PosSessionCreated,
+    PosSessionRevoked,
+    GuardiansUpdated,
+    OwnerRecoveryInitiated,
+    OwnerRecoveryApproved,
+    OwnerRecoveryVetoed,
+    OwnerRecoveryExecuted,
+    AdminRoleChanged,
+    VariantRegistered,
+    VariantUpdated,
+    ProductsBatchRegistered,
+    ProductsBatchUpdated,
+    ProductClosed,
+    ProductBarcodeSet,
+    LoyaltyPointsRedeemed,
+}
+
+// Creates the audit log for a store registered before audit logs existed
+#[derive(Accounts)]
+pub struct InitializeAuditLog<'info> {
+    #[account(has_one = owner)]
+    pub store: Account<'info, Store>,
+    #[account(
+        init,
+        payer = owner,
+        space = AuditLog::LEN,
+        seeds = [b"audit_log", store.key().as_ref()],
+        bump
+    )]
+    pub audit_log: Account<'info, AuditLog>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn empty_log() -> AuditLog {
+        AuditLog {
+            scope: Pubkey::new_unique(),
+            next_index: 0,
+            total_entries: 0,
+            entries: Vec::new(),
+            bump: 255,
+        }
+    }
+
+    #[test]
+    fn record_appends_until_full() {
+        let mut log = empty_log();
+        let actor = Pubkey::new_unique();
+        for i in 0..AuditLog::CAPACITY {
+            log.record_at(
+                actor,
+                AuditAction::ProductRegistered,
+                Pubkey::new_unique(),
+                i as i64,
+            );
+        }
+        assert_eq!(log.entries.len(), AuditLog::CAPACITY);
+        assert_eq!(log.next_index, 0);
+        assert_eq!(log.total_entries, AuditLog::CAPACITY as u64);
+        assert_eq!(log.entries[0].timestamp, 0);
+    }
+
+    #[test]
+    fn record_wraps_and_overwrites_oldest() {
+        let mut log = empty_log();
+        let actor = Pubkey::new_unique();
+        for i in 0..AuditLog::CAPACITY + 3 {
+            log.record_at(
+                actor,
+                AuditAction::ProductUpdated,
+                Pubkey::new_unique(),
+                i as i64,
+            );
+        }
+        assert_eq!(log.entries.len(), AuditLog::CAPACITY);
+        assert_eq!(log.next_index, 3);
+        assert_eq!(log.total_entries, AuditLog::CAPACITY as u64 + 3);
+        // Slots 0..3 hold the newest entries, slot 3 the oldest survivor
+        assert_eq!(log.entries[0].timestamp, AuditLog::CAPACITY as i64);
+        assert_eq!(log.entries[2].timestamp, AuditLog::CAPACITY as i64 + 2);
+        assert_eq!(log.entries[3].timestamp, 3);
+    }
+
+    #[test]
+    fn record_keeps_actor_action_and_target() {
+        let mut log = empty_log();
+        let actor = Pubkey::new_unique();
+        let target = Pubkey::new_unique();
+        log.record_at(actor, AuditAction::AdminRemoved, target, 42);
+        let entry = &log.entries[0];
+        assert_eq!(entry.actor, actor);
+        assert_eq!(entry.action, AuditAction::AdminRemoved);
+        assert_eq!(entry.target, target);
+        assert_eq!(entry.timestamp, 42);
+    }
+}
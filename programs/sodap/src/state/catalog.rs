@@ -0,0 +1,167 @@
+use crate::error::CustomError;
+use anchor_lang::prelude::*;
+
+// Head of a store's catalog: tracks the chain of `CatalogPage` accounts so
+// clients can enumerate products page by page, in registration order
+#[account]
+pub struct CatalogIndex {
+    pub store: Pubkey,
+    pub page_count: u32,
+    /// Number of products on the last page, which is the only one appended to
+    pub last_page_len: u16,
+    pub total_products: u64,
+    pub bump: u8,
+}
+
+impl CatalogIndex {
+    pub const LEN: usize = 8 + 32 + 4 + 2 + 8 + 1;
+
+    /// Page that the next `count` products go to; a new page is started when
+    /// they don't all fit on the last one
+    pub fn append_page(&self, count: usize) -> u32 {
+        if self.page_count == 0 || self.last_page_len as usize + count > CatalogPage::CAPACITY {
+            self.page_count
+        } else {
+            self.page_count - 1
+        }
+    }
+
+    /// Appends `product` to `page`, which must be the page chosen by `append_page`
+    pub fn push(&mut self, page: &mut CatalogPage, product: Pubkey) -> Result<()> {
+        if page.page_index == self.page_count {
+            self.page_count += 1;
+            self.last_page_len = 0;
+        }
+        require!(
+            page.page_index + 1 == self.page_count && page.products.len() < CatalogPage::CAPACITY,
+            CustomError::CatalogPageFull
+        );
+        page.products.push(product);
+        self.last_page_len += 1;
+        self.total_products += 1;
+        Ok(())
+    }
+
+    /// Removes `product` from `page`, keeping the order of the rest
+    pub fn remove(&mut self, page: &mut CatalogPage, product: &Pubkey) -> Result<()> {
+        let position = page
+            .products
+            .iter()
+            .position(|p| p == product)
+            .ok_or(CustomError::ProductNotFound)?;
+        page.products.remove(position);
+        if page.page_index + 1 == self.page_count {
+            self.last_page_len -= 1;
+        }
+        self.total_products -= 1;
+        Ok(())
+    }
+}
+
+// One page of product addresses, PDA [b"catalog_page", store, page_index]
+#[account]
+pub struct CatalogPage {
+    pub store: Pubkey,
+    pub page_index: u32,
+    pub products: Vec<Pubkey>,
+    pub bump: u8,
+}
+
+impl CatalogPage {
+    pub const CAPACITY: usize = 50;
+    pub const LEN: usize = 8 + 32 + 4 + (4 + 32 * Self::CAPACITY) + 1;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index() -> CatalogIndex {
+        CatalogIndex {
+            store: Pubkey::new_unique(),
+            page_count: 0,
+            last_page_len: 0,
+            total_products: 0,
+            bump: 255,
+        }
+    }
+
+    fn page(page_index: u32) -> CatalogPage {
+        CatalogPage {
+            store: Pubkey::default(),
+            page_index,
+            products: Vec::new(),
+            bump: 255,
+        }
+    }
+
+    #[test]
+    fn first_product_opens_page_zero() {
+        let mut idx = index();
+        assert_eq!(idx.append_page(1), 0);
+        let mut p = page(0);
+        idx.push(&mut p, Pubkey::new_unique()).unwrap();
+        assert_eq!(idx.page_count, 1);
+        assert_eq!(idx.last_page_len, 1);
+        assert_eq!(idx.total_products, 1);
+    }
+
+    #[test]
+    fn full_page_rolls_over() {
+        let mut idx = index();
+        let mut first = page(0);
+        for _ in 0..CatalogPage::CAPACITY {
+            assert_eq!(idx.append_page(1), 0);
+            idx.push(&mut first, Pubkey::new_unique()).unwrap();
+        }
+        assert_eq!(idx.last_page_len as usize, CatalogPage::CAPACITY);
+
+        assert_eq!(idx.append_page(1), 1);
+        let mut second = page(1);
+        idx.push(&mut second, Pubkey::new_unique()).unwrap();
+        assert_eq!(idx.page_count, 2);
+        assert_eq!(idx.last_page_len, 1);
+        assert_eq!(idx.total_products, CatalogPage::CAPACITY as u64 + 1);
+
+        // Earlier pages are closed to appends
+        assert!(idx.push(&mut first, Pubkey::new_unique()).is_err());
+    }
+
+    #[test]
+    fn batch_that_does_not_fit_starts_a_new_page() {
+        let mut idx = index();
+        let mut first = page(0);
+        for _ in 0..CatalogPage::CAPACITY - 3 {
+            idx.push(&mut first, Pubkey::new_unique()).unwrap();
+        }
+        assert_eq!(idx.append_page(3), 0);
+        assert_eq!(idx.append_page(4), 1);
+    }
+
+    #[test]
+    fn remove_tracks_last_page_len() {
+        let mut idx = index();
+        let mut first = page(0);
+        let products: Vec<Pubkey> = (0..CatalogPage::CAPACITY)
+            .map(|_| Pubkey::new_unique())
+            .collect();
+        for product in &products {
+            idx.push(&mut first, *product).unwrap();
+        }
+        let mut second = page(1);
+        let last = Pubkey::new_unique();
+        idx.push(&mut second, last).unwrap();
+
+        // Removing from an earlier page leaves the last page count alone
+        idx.remove(&mut first, &products[10]).unwrap();
+        assert_eq!(idx.last_page_len, 1);
+        assert_eq!(first.products.len(), CatalogPage::CAPACITY - 1);
+        assert_eq!(first.products[10], products[11]);
+
+        idx.remove(&mut second, &last).unwrap();
+        assert_eq!(idx.last_page_len, 0);
+        assert_eq!(idx.total_products, CatalogPage::CAPACITY as u64 - 1);
+
+        assert!(idx.remove(&mut second, &last).is_err());
+    }
+}
@@ -0,0 +1,505 @@
+use super::audit::AuditLog;
+use super::product::Purchase;
+use super::session::PosSession;
+use super::store::Store;
+use crate::error::CustomError;
+use crate::types::StorePermission;
+use anchor_lang::prelude::*;
+
+// Holds buyer payments for a store until they are released or refunded
+#[account]
+pub struct Escrow {
+    pub store: Pubkey,
+    pub balance: u64,
+    pub bump: u8,
+    /// Day number (unix timestamp / 86400) that `paid_out_today` belongs to
+    pub current_day: i64,
+    /// Paid out today without a proposal
+    pub paid_out_today: u64,
+}
+
+impl Escrow {
+    pub const LEN: usize = 8 + 32 + 8 + 1 + 8 + 8;
+
+    /// Counts a payout made without a proposal against `daily_limit`, so the
+    /// policy threshold can't be sidestepped by splitting a withdrawal up
+    pub fn charge_direct_payout(&mut self, amount: u64, daily_limit: u64, now: i64) -> Result<()> {
+        let day = now / 86_400;
+        if day != self.current_day {
+            self.current_day = day;
+            self.paid_out_today = 0;
+        }
+        let paid_out = self
+            .paid_out_today
+            .checked_add(amount)
+            .ok_or(CustomError::ArithmeticError)?;
+        require!(paid_out <= daily_limit, CustomError::ApprovalRequired);
+        self.paid_out_today = paid_out;
+        Ok(())
+    }
+}
+
+// Per-store withdrawal policy: once releases and refunds made directly add up
+// to more than `threshold` in a day, further ones must go through an
+// `EscrowProposal` approved by `required_approvals` store admins
+#[account]
+pub struct EscrowPolicy {
+    pub store: Pubkey,
+    pub threshold: u64,
+    pub required_approvals: u8,
+    pub proposal_count: u64,
+    pub bump: u8,
+}
+
+impl EscrowPolicy {
+    pub const LEN: usize = 8 + 32 + 8 + 1 + 8 + 1;
+    pub const MAX_APPROVALS: u8 = 10;
+
+    /// Reads the policy from its PDA, returning `None` if the store never configured one
+    pub fn load(info: &AccountInfo) -> Result<Option<Self>> {
+        if info.owner != &crate::ID || info.data_is_empty() {
+            return Ok(None);
+        }
+        let data = info.try_borrow_data()?;
+        Ok(Some(Self::try_deserialize(&mut &data[..])?))
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub enum EscrowAction {
+    Release {
+        amount: u64,
+    },
+    /// Refund against a purchase receipt, paid to the receipt's buyer
+    Refund {
+        receipt: Pubkey,
+        amount: u64,
+    },
+    UpdatePolicy {
+        threshold: u64,
+        required_approvals: u8,
+    },
+}
+
+impl EscrowAction {
+    pub const LEN: usize = 1 + 32 + 8;
+
+    /// Permission an admin needs to propose or approve this action
+    pub fn required_permission(&self) -> StorePermission {
+        match self {
+            EscrowAction::Refund { .. } => StorePermission::IssueRefunds,
+            EscrowAction::Release { .. } | EscrowAction::UpdatePolicy { .. } => {
+                StorePermission::ReleaseEscrow
+            }
+        }
+    }
+}
+
+// Pending escrow action collecting admin approvals; closed when executed or cancelled
+#[account]
+pub struct EscrowProposal {
+    pub store: Pubkey,
+    pub proposal_id: u64,
+    pub proposer: Pubkey,
+    pub action: EscrowAction,
+    pub approvals: Vec<Pubkey>,
+    pub created_at: i64,
+    pub bump: u8,
+}
+
+impl EscrowProposal {
+    pub const LEN: usize = 8
+        + 32
+        + 8
+        + 32
+        + EscrowAction::LEN
+        + (4 + 32 * EscrowPolicy::MAX_APPROVALS as usize)
+        + 8
+        + 1;
+
+    /// Approvals from admins who still hold the permission the action needs
+    /// and already held it when the proposal was opened, so wallets granted
+    /// the permission afterwards can't be used to make up the numbers
+    pub fn valid_approvals(&self, store: &Store) -> usize {
+        let permission = self.action.required_permission();
+        self.approvals
+            .iter()
+            .filter(|a| store.held_permission_before(a, permission, self.created_at))
+            .count()
+    }
+}
+
+// Escrow approval events
+#[event]
+pub struct EscrowPolicyUpdated {
+    pub store: Pubkey,
+    pub threshold: u64,
+    pub required_approvals: u8,
+    pub updated_at: i64,
+}
+
+#[event]
+pub struct EscrowProposalCreated {
+    pub store: Pubkey,
+    pub proposal_id: u64,
+    pub proposer: Pubkey,
+    pub action: EscrowAction,
+    pub created_at: i64,
+}
+
+#[event]
+pub struct EscrowProposalApproved {
+    pub store: Pubkey,
+    pub proposal_id: u64,
+    pub approver: Pubkey,
+    pub approvals: u8,
+}
+
+#[event]
+pub struct EscrowProposalExecuted {
+    pub store: Pubkey,
+    pub proposal_id: u64,
+    pub action: EscrowAction,
+    pub executed_at: i64,
+}
+
+#[event]
+pub struct EscrowProposalCancelled {
+    pub store: Pubkey,
+    pub proposal_id: u64,
+    pub cancelled_by: Pubkey,
+    pub cancelled_at: i64,
+}
+
+#[derive(Accounts)]
+pub struct ReleaseEscrow<'info> {
+    #[account(mut, constraint = !store.is_suspended() @ CustomError::StoreSuspended)]
+    pub store: Account<'info, Store>,
+    #[account(
+        mut,
+        seeds = [b"audit_log", store.key().as_ref()],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Account<'info, AuditLog>,
+    #[account(
+        constraint = store.has_permission(&authority.key(), StorePermission::ReleaseEscrow) @ CustomError::Unauthorized
+    )]
+    pub authority: Signer<'info>,
+    /// The store owner who receives funds
+    #[account(mut, address = store.owner @ CustomError::Unauthorized)]
+    /// CHECK: Payout destination, pinned to the store owner
+    pub store_owner: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [b"escrow", store.key().as_ref()],
+        bump = escrow_account.bump,
+        constraint = escrow_account.store == store.key() @ CustomError::Unauthorized
+    )]
+    pub escrow_account: Account<'info, Escrow>,
+    /// CHECK: The store's approval policy PDA; left uninitialized when the store has none
+    #[account(seeds = [b"escrow_policy", store.key().as_ref()], bump)]
+    pub escrow_policy: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RefundEscrow<'info> {
+    #[account(mut)]
+    pub store: Account<'info, Store>,
+    #[account(
+        mut,
+        seeds = [b"audit_log", store.key().as_ref()],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Account<'info, AuditLog>,
+    /// Store admin or POS session key authorizing the refund; checked in the handler
+    pub authority: Signer<'info>,
+    /// Present when `authority` is a POS session key rather than store staff
+    #[account(
+        mut,
+        seeds = [b"pos_session", store.key().as_ref(), authority.key().as_ref()],
+        bump = pos_session.bump
+    )]
+    pub pos_session: Option<Account<'info, PosSession>>,
+    /// Receipt of the order being refunded
+    #[account(
+        mut,
+        constraint = receipt.store == store.key() @ CustomError::InvalidStore
+    )]
+    pub receipt: Account<'info, Purchase>,
+    /// The buyer on the receipt, who receives the refund
+    #[account(mut, address = receipt.buyer @ CustomError::Unauthorized)]
+    /// CHECK: Refund destination, pinned to the receipt's buyer
+    pub buyer: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [b"escrow", store.key().as_ref()],
+        bump = escrow_account.bump,
+        constraint = escrow_account.store == store.key() @ CustomError::Unauthorized
+    )]
+    pub escrow_account: Account<'info, Escrow>,
+    /// CHECK: The store's approval policy PDA; left uninitialized when the store has none
+    #[account(seeds = [b"escrow_policy", store.key().as_ref()], bump)]
+    pub escrow_policy: UncheckedAccount<'info>,
+}
+
+// Set or tighten the store's withdrawal policy; loosening it needs a proposal
+#[derive(Accounts)]
+pub struct SetEscrowPolicy<'info> {
+    #[account(has_one = owner)]
+    pub store: Account<'info, Store>,
+    #[account(
+        mut,
+        seeds = [b"audit_log", store.key().as_ref()],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Account<'info, AuditLog>,
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = EscrowPolicy::LEN,
+        seeds = [b"escrow_policy", store.key().as_ref()],
+        bump
+    )]
+    pub escrow_policy: Account<'info, EscrowPolicy>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ProposeEscrowAction<'info> {
+    pub store: Account<'info, Store>,
+    #[account(
+        mut,
+        seeds = [b"audit_log", store.key().as_ref()],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Account<'info, AuditLog>,
+    #[account(
+        mut,
+        seeds = [b"escrow_policy", store.key().as_ref()],
+        bump = escrow_policy.bump
+    )]
+    pub escrow_policy: Account<'info, EscrowPolicy>,
+    #[account(
+        init,
+        payer = proposer,
+        space = EscrowProposal::LEN,
+        seeds = [
+            b"escrow_proposal",
+            store.key().as_ref(),
+            &escrow_policy.proposal_count.to_le_bytes()
+        ],
+        bump
+    )]
+    pub proposal: Account<'info, EscrowProposal>,
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ApproveEscrowProposal<'info> {
+    pub store: Account<'info, Store>,
+    #[account(
+        mut,
+        seeds = [b"audit_log", store.key().as_ref()],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Account<'info, AuditLog>,
+    #[account(
+        mut,
+        seeds = [
+            b"escrow_proposal",
+            store.key().as_ref(),
+            &proposal.proposal_id.to_le_bytes()
+        ],
+        bump = proposal.bump,
+        has_one = store
+    )]
+    pub proposal: Account<'info, EscrowProposal>,
+    pub approver: Signer<'info>,
+}
+
+// Anyone may execute a proposal once it has enough approvals
+#[derive(Accounts)]
+pub struct ExecuteEscrowProposal<'info> {
+    pub store: Account<'info, Store>,
+    #[account(
+        mut,
+        seeds = [b"audit_log", store.key().as_ref()],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Account<'info, AuditLog>,
+    #[account(
+        mut,
+        seeds = [b"escrow_policy", store.key().as_ref()],
+        bump = escrow_policy.bump
+    )]
+    pub escrow_policy: Account<'info, EscrowPolicy>,
+    #[account(
+        mut,
+        seeds = [
+            b"escrow_proposal",
+            store.key().as_ref(),
+            &proposal.proposal_id.to_le_bytes()
+        ],
+        bump = proposal.bump,
+        has_one = store,
+        has_one = proposer,
+        close = proposer
+    )]
+    pub proposal: Account<'info, EscrowProposal>,
+    /// Required for release and refund proposals
+    #[account(
+        mut,
+        seeds = [b"escrow", store.key().as_ref()],
+        bump = escrow_account.bump,
+        constraint = escrow_account.store == store.key() @ CustomError::Unauthorized
+    )]
+    pub escrow_account: Option<Account<'info, Escrow>>,
+    /// CHECK: Payout destination, checked against the proposal's action
+    #[account(mut)]
+    pub recipient: Option<AccountInfo<'info>>,
+    /// Required for refund proposals: the receipt named in the proposal
+    #[account(
+        mut,
+        constraint = receipt.store == store.key() @ CustomError::InvalidStore
+    )]
+    pub receipt: Option<Account<'info, Purchase>>,
+    /// CHECK: Receives the proposal rent back
+    #[account(mut)]
+    pub proposer: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CancelEscrowProposal<'info> {
+    pub store: Account<'info, Store>,
+    #[account(
+        mut,
+        seeds = [b"audit_log", store.key().as_ref()],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Account<'info, AuditLog>,
+    #[account(
+        mut,
+        seeds = [
+            b"escrow_proposal",
+            store.key().as_ref(),
+            &proposal.proposal_id.to_le_bytes()
+        ],
+        bump = proposal.bump,
+        has_one = store,
+        has_one = proposer,
+        close = proposer
+    )]
+    pub proposal: Account<'info, EscrowProposal>,
+    /// CHECK: Receives the proposal rent back
+    #[account(mut)]
+    pub proposer: AccountInfo<'info>,
+    /// The proposer or the store owner
+    pub authority: Signer<'info>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::store::AdminRole;
+    use crate::types::{AdminRoleType, LoyaltyConfig};
+
+    const DAY: i64 = 86_400;
+
+    fn escrow() -> Escrow {
+        Escrow {
+            store: Pubkey::new_unique(),
+            balance: 0,
+            bump: 255,
+            current_day: 0,
+            paid_out_today: 0,
+        }
+    }
+
+    fn role(admin_pubkey: Pubkey, role_type: AdminRoleType, granted_at: i64) -> AdminRole {
+        AdminRole {
+            admin_pubkey,
+            role_type,
+            permissions: StorePermission::ALL,
+            expires_at: None,
+            granted_at,
+        }
+    }
+
+    fn store(owner: Pubkey, admin_roles: Vec<AdminRole>) -> Store {
+        Store {
+            owner,
+            name: String::new(),
+            description: String::new(),
+            logo_uri: String::new(),
+            loyalty_config: LoyaltyConfig {
+                points_per_dollar: 0,
+                redemption_rate: 0,
+            },
+            is_active: true,
+            revenue: 0,
+            admin_roles,
+            suspension: None,
+            guardian_set: None,
+            session_epoch: 0,
+        }
+    }
+
+    fn proposal(approvals: Vec<Pubkey>, created_at: i64) -> EscrowProposal {
+        EscrowProposal {
+            store: Pubkey::new_unique(),
+            proposal_id: 0,
+            proposer: approvals[0],
+            action: EscrowAction::Release { amount: 1 },
+            approvals,
+            created_at,
+            bump: 255,
+        }
+    }
+
+    #[test]
+    fn direct_payouts_are_capped_per_day() {
+        let now = 100 * DAY;
+        let mut e = escrow();
+        e.charge_direct_payout(600, 1_000, now).unwrap();
+        assert_eq!(
+            e.charge_direct_payout(401, 1_000, now + 60).unwrap_err(),
+            CustomError::ApprovalRequired.into()
+        );
+        e.charge_direct_payout(400, 1_000, now + 60).unwrap();
+
+        e.charge_direct_payout(1_000, 1_000, now + DAY).unwrap();
+        assert_eq!(e.paid_out_today, 1_000);
+    }
+
+    #[test]
+    fn approvals_from_grants_after_the_proposal_do_not_count() {
+        let owner = Pubkey::new_unique();
+        let late = Pubkey::new_unique();
+        let s = store(
+            owner,
+            vec![
+                role(owner, AdminRoleType::Owner, 1_000),
+                role(late, AdminRoleType::Manager, 2_001),
+            ],
+        );
+        assert_eq!(proposal(vec![owner, late], 2_000).valid_approvals(&s), 1);
+    }
+
+    #[test]
+    fn approvals_from_grants_in_the_same_second_count() {
+        let owner = Pubkey::new_unique();
+        let admin = Pubkey::new_unique();
+        let s = store(
+            owner,
+            vec![
+                role(owner, AdminRoleType::Owner, 2_000),
+                role(admin, AdminRoleType::Manager, 2_000),
+            ],
+        );
+        assert_eq!(proposal(vec![owner, admin], 2_000).valid_approvals(&s), 2);
+    }
+}
@@ -1,5 +1,12 @@
+use super::audit::AuditLog;
+use super::escrow::Escrow;
+use super::product::Purchase;
+use super::session::PosSession;
 use super::store::Store;
+use crate::error::CustomError;
+use crate::types::StorePermission;
 use anchor_lang::prelude::*;
+use anchor_spl::token::{Mint, Token, TokenAccount};
 
 // Event: user earned loyalty points
 #[event]
@@ -25,6 +32,7 @@ pub struct LoyaltyPointsRedeemed {
 }
 
 // Event: loyalty tokens were minted
+#[allow(dead_code)]
 #[event]
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct LoyaltyTokensMinted {
@@ -37,57 +45,158 @@ pub struct LoyaltyTokensMinted {
 // Account that stores information about the loyalty token mint
 #[account]
 pub struct LoyaltyMint {
-    pub store: Pubkey,     // The store this mint is associated with
-    pub authority: Pubkey, // Authority who can mint/redeem tokens
-    pub total_supply: u64, // Total loyalty tokens in circulation
+    pub store: Pubkey,              // The store this mint is associated with
+    pub mint: Pubkey,               // The SPL token mint backing the points
+    pub authority: Pubkey,          // Authority who can mint tokens
+    pub points_per_sol: u64,        // Points earned per whole SOL spent
+    pub redemption_rate: u64,       // Whole points needed to redeem 1 SOL
+    pub total_points_issued: u64,   // Total loyalty points ever minted
+    pub total_points_redeemed: u64, // Total loyalty points burned on redemption
+    pub is_token2022: bool,         // Flag to indicate if this is using token_interface
+    pub bump: u8,                   // This PDA is the SPL mint authority
 }
 
-// Context: Redeem loyalty tokens from the mint
+impl LoyaltyMint {
+    pub const LEN: usize = 8 + 32 + 32 + 32 + 8 + 8 + 8 + 8 + 1 + 1;
+}
+
+// Context: Initialize the loyalty mint account and SPL mint for a store
 #[derive(Accounts)]
-pub struct RedeemLoyaltyPoints<'info> {
-    #[account(mut)]
+pub struct InitializeLoyaltyMint<'info> {
+    #[account(
+        mut,
+        constraint = store.has_permission(&payer.key(), StorePermission::MintLoyalty) @ CustomError::Unauthorized
+    )]
     pub store: Account<'info, Store>,
     #[account(
         mut,
+        seeds = [b"audit_log", store.key().as_ref()],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Account<'info, AuditLog>,
+    #[account(
+        init,
+        payer = payer,
+        space = LoyaltyMint::LEN,
         seeds = [b"loyalty_mint", store.key().as_ref()],
-        bump,
-        has_one = store,
-        has_one = authority
+        bump
     )]
-    pub loyalty_mint: Account<'info, LoyaltyMint>,
-    pub authority: Signer<'info>,
+    pub loyalty_mint_account: Account<'info, LoyaltyMint>,
+    #[account(
+        init,
+        payer = payer,
+        mint::decimals = 6,
+        mint::authority = loyalty_mint_account,
+    )]
+    pub token_mint: Account<'info, Mint>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
 }
 
-// Context: Mint new loyalty tokens
+// Context: Mint loyalty tokens for a purchase that wasn't credited at checkout
 #[derive(Accounts)]
 pub struct MintLoyaltyTokens<'info> {
-    #[account(mut)]
+    #[account(
+        mut,
+        constraint = store.is_active @ CustomError::StoreInactive,
+        constraint = !store.is_suspended() @ CustomError::StoreSuspended
+    )]
     pub store: Account<'info, Store>,
+    #[account(
+        mut,
+        seeds = [b"audit_log", store.key().as_ref()],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Account<'info, AuditLog>,
     #[account(
         mut,
         seeds = [b"loyalty_mint", store.key().as_ref()],
-        bump,
-        has_one = store,
-        has_one = authority
+        bump = loyalty_mint_account.bump,
+        constraint = loyalty_mint_account.store == store.key() @ CustomError::Unauthorized
     )]
-    pub loyalty_mint: Account<'info, LoyaltyMint>,
+    pub loyalty_mint_account: Account<'info, LoyaltyMint>,
+    /// Receipt of the purchase the points are earned on; each is credited once
+    #[account(
+        mut,
+        constraint = receipt.store == store.key() @ CustomError::InvalidStore,
+        constraint = !receipt.loyalty_credited @ CustomError::InvalidParameters
+    )]
+    pub receipt: Account<'info, Purchase>,
+    #[account(
+        mut,
+        address = loyalty_mint_account.mint @ CustomError::InvalidMint
+    )]
+    pub token_mint: Account<'info, Mint>,
+    #[account(
+        mut,
+        token::mint = token_mint,
+        token::authority = recipient,
+    )]
+    pub token_account: Account<'info, TokenAccount>,
+    /// Store admin or POS session key; checked in the handler
     pub authority: Signer<'info>,
+    /// Present when `authority` is a POS session key rather than store staff
+    #[account(
+        mut,
+        seeds = [b"pos_session", store.key().as_ref(), authority.key().as_ref()],
+        bump = pos_session.bump
+    )]
+    pub pos_session: Option<Account<'info, PosSession>>,
+    /// CHECK: The buyer on the receipt, who owns `token_account`
+    #[account(address = receipt.buyer @ CustomError::Unauthorized)]
+    pub recipient: AccountInfo<'info>,
+    /// CHECK: Only used for logging who earned the points
+    pub buyer: AccountInfo<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
 }
 
-// Context: Initialize the loyalty mint account for a store
+// Context: Redeem loyalty tokens, optionally for SOL from the store escrow
 #[derive(Accounts)]
-pub struct InitializeLoyaltyMint<'info> {
-    #[account(mut)]
+pub struct RedeemLoyaltyPoints<'info> {
+    #[account(mut, constraint = !store.is_suspended() @ CustomError::StoreSuspended)]
     pub store: Account<'info, Store>,
     #[account(
-        init,
-        payer = authority,
-        space = 8 + 32 + 32 + 8,
+        mut,
+        seeds = [b"audit_log", store.key().as_ref()],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Account<'info, AuditLog>,
+    #[account(
+        mut,
         seeds = [b"loyalty_mint", store.key().as_ref()],
-        bump
+        bump = loyalty_mint_account.bump,
+        constraint = loyalty_mint_account.store == store.key() @ CustomError::Unauthorized
+    )]
+    pub loyalty_mint_account: Account<'info, LoyaltyMint>,
+    #[account(
+        mut,
+        address = loyalty_mint_account.mint @ CustomError::InvalidMint
     )]
-    pub loyalty_mint: Account<'info, LoyaltyMint>,
+    pub token_mint: Account<'info, Mint>,
+    #[account(
+        mut,
+        token::mint = token_mint,
+        token::authority = user,
+    )]
+    pub token_account: Account<'info, TokenAccount>,
     #[account(mut)]
-    pub authority: Signer<'info>,
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"escrow", store.key().as_ref()],
+        bump = escrow_account.bump,
+        constraint = escrow_account.store == store.key() @ CustomError::Unauthorized
+    )]
+    pub escrow_account: Option<Account<'info, Escrow>>,
+    /// Store admin with `ReleaseEscrow` co-signing a redemption for SOL
+    pub escrow_authority: Option<Signer<'info>>,
+    /// CHECK: The store's approval policy PDA; left uninitialized when the store has none
+    #[account(seeds = [b"escrow_policy", store.key().as_ref()], bump)]
+    pub escrow_policy: UncheckedAccount<'info>,
+    pub token_program: Program<'info, Token>,
     pub system_program: Program<'info, System>,
 }
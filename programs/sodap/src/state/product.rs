@@ -1,8 +1,14 @@
+use super::audit::AuditLog;
+use super::catalog::{CatalogIndex, CatalogPage};
+use super::escrow::Escrow;
+use super::loyalty::LoyaltyMint;
+use super::session::PosSession;
 use super::store::Store;
 use crate::error::CustomError;
-use crate::state::Escrow;
-use crate::types::{AnomalyFlag, TokenizedType, TransactionStatus};
+use crate::types::{AnomalyFlag, StorePermission, TokenizedType, TransactionStatus};
+use crate::ProductAttribute;
 use anchor_lang::prelude::*;
+use anchor_spl::token::{Mint, Token, TokenAccount};
 
 #[account]
 pub struct Product {
@@ -14,15 +20,128 @@ pub struct Product {
     pub metadata_uri: String,
     pub store: Pubkey,
     pub authority: Pubkey,
+    /// Filterable key/value pairs (brand, size, allergens, ...); the account
+    /// is sized to fit them and resized when they change
+    pub attributes: Vec<ProductAttribute>,
+    /// Barcode (EAN/UPC) or SKU registered for the product in its `SkuEntry`
+    pub barcode: Option<String>,
+    /// Number of `ProductVariant` accounts registered under the product
+    pub variant_count: u16,
 }
 
 impl Product {
-    pub const LEN: usize = 8 + 16 + 8 + 8 + 1 + (4 + 200) + 32 + 32;
+    pub const MAX_ATTRIBUTES: usize = 10;
+    pub const MAX_ATTRIBUTE_NAME_LEN: usize = 32;
+    pub const MAX_ATTRIBUTE_VALUE_LEN: usize = 64;
+    pub const MAX_METADATA_URI_LEN: usize = 200;
+    pub const MAX_BATCH_SIZE: usize = 20;
+    // Size with no attributes
+    pub const LEN: usize = 8
+        + 16
+        + 8
+        + 8
+        + 1
+        + (4 + Self::MAX_METADATA_URI_LEN)
+        + 32
+        + 32
+        + 4
+        + (1 + 4 + SkuEntry::MAX_BARCODE_LEN)
+        + 2;
+
+    pub fn space(attributes: &[ProductAttribute]) -> usize {
+        Self::LEN + attributes.iter().map(|a| a.space()).sum::<usize>()
+    }
+}
+
+// Maps a scanned barcode or SKU to a product of the store,
+// PDA [b"sku", store, barcode]
+#[account]
+pub struct SkuEntry {
+    pub store: Pubkey,
+    pub barcode: String,
+    pub product_uuid: [u8; 16],
+    pub bump: u8,
+}
+
+impl SkuEntry {
+    // Barcodes are used as a PDA seed, which is capped at 32 bytes
+    pub const MAX_BARCODE_LEN: usize = 32;
+    pub const LEN: usize = 8 + 32 + (4 + Self::MAX_BARCODE_LEN) + 16 + 1;
+
+    /// Seed bytes for a new entry's PDA. Init accounts are validated before
+    /// any `constraint` runs, so the length is checked here: deriving the PDA
+    /// from a seed over 32 bytes aborts the program instead of failing cleanly.
+    pub fn seed(barcode: Option<&str>) -> Result<&[u8]> {
+        let barcode = barcode.unwrap_or_default();
+        require!(
+            barcode.len() <= Self::MAX_BARCODE_LEN,
+            CustomError::InvalidBarcode
+        );
+        Ok(barcode.as_bytes())
+    }
+}
+
+// One product to create in `batch_register_products`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct ProductBatchEntry {
+    pub product_uuid: [u8; 16],
+    pub price: u64,
+    pub stock: u64,
+    pub tokenized_type: TokenizedType,
+    pub metadata_uri: String,
+    pub attributes: Vec<ProductAttribute>,
+    /// Registers the product under this barcode; its `SkuEntry` PDA follows
+    /// the product PDA in `remaining_accounts`
+    pub barcode: Option<String>,
+}
+
+// Changes to one product in `batch_update_products`; `None` fields are left as is
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct ProductBatchUpdate {
+    pub product_uuid: [u8; 16],
+    pub new_price: Option<u64>,
+    pub new_stock: Option<u64>,
+    pub new_metadata_uri: Option<String>,
+    pub new_tokenized_type: Option<TokenizedType>,
+    pub new_attributes: Option<Vec<ProductAttribute>>,
+}
+
+// A purchasable variant of a product (size, color, ...) with its own price,
+// stock and SKU
+#[account]
+pub struct ProductVariant {
+    pub product: Pubkey,
+    pub store: Pubkey,
+    pub variant_id: [u8; 16],
+    pub sku: String,
+    pub options: Vec<ProductAttribute>,
+    pub price: u64,
+    pub stock: u64,
+    pub is_active: bool,
+    pub bump: u8,
+}
+
+impl ProductVariant {
+    pub const MAX_SKU_LEN: usize = 32;
+    pub const MAX_OPTIONS: usize = 3;
+    pub const MAX_OPTION_LEN: usize = 32;
+    pub const LEN: usize = 8
+        + 32
+        + 32
+        + 16
+        + (4 + Self::MAX_SKU_LEN)
+        + (4 + Self::MAX_OPTIONS * (4 + Self::MAX_OPTION_LEN + 4 + Self::MAX_OPTION_LEN))
+        + 8
+        + 8
+        + 1
+        + 1;
 }
 
 #[account]
 pub struct Purchase {
     pub product_uuids: Vec<[u8; 16]>,
+    /// Variant bought for each line, `None` for products without variants
+    pub variant_ids: Vec<Option<[u8; 16]>>,
     pub quantities: Vec<u64>,
     pub total_paid: u64,
     pub gas_fee: u64,
@@ -31,25 +150,113 @@ pub struct Purchase {
     pub store: Pubkey,
     pub buyer: Pubkey,
     pub ts: i64,
+    pub order_id: u64,
+    pub tip: u64,
+    /// Set once loyalty points were minted for this order, at checkout or later
+    pub loyalty_credited: bool,
+    /// Lamports refunded to the buyer so far
+    pub refunded: u64,
 }
 
 impl Purchase {
-    pub const LEN: usize = 8 + (4 + 10 * 16) + (4 + 10 * 8) + 8 + 8 + 1 + 1 + 32 + 32 + 8;
+    pub const MAX_ITEMS: usize = 10;
+    pub const LEN: usize = 8
+        + (4 + Self::MAX_ITEMS * 16)
+        + (4 + Self::MAX_ITEMS * 17)
+        + (4 + Self::MAX_ITEMS * 8)
+        + 8
+        + 8
+        + 1
+        + 1
+        + 32
+        + 32
+        + 8
+        + 8
+        + 8
+        + 1
+        + 8;
+
+    /// What is left to refund of the cart total and tip the buyer paid
+    pub fn refundable(&self) -> u64 {
+        self.total_paid
+            .saturating_add(self.tip)
+            .saturating_sub(self.refunded)
+    }
+}
+
+// Per-buyer-per-store order counter; receipt PDAs are keyed by its value so
+// a buyer's order history can be enumerated from 0..order_count
+#[account]
+pub struct OrderCounter {
+    pub store: Pubkey,
+    pub buyer: Pubkey,
+    pub order_count: u64,
+}
+
+impl OrderCounter {
+    pub const LEN: usize = 8 + 32 + 32 + 8;
 }
 
 #[derive(Accounts)]
-#[instruction(product_uuid: [u8; 16])]
+#[instruction(
+    product_uuid: [u8; 16],
+    price: u64,
+    stock: u64,
+    tokenized_type: TokenizedType,
+    metadata_uri: String,
+    attributes: Vec<ProductAttribute>,
+    barcode: Option<String>
+)]
 pub struct RegisterProduct<'info> {
-    #[account(mut)]
+    #[account(
+        mut,
+        constraint = store.is_active @ CustomError::StoreInactive,
+        constraint = store.has_permission(&authority.key(), StorePermission::ManageProducts) @ CustomError::Unauthorized
+    )]
     pub store: Account<'info, Store>,
+    #[account(
+        mut,
+        seeds = [b"audit_log", store.key().as_ref()],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Account<'info, AuditLog>,
     #[account(
         init,
         payer = authority,
-        space = Product::LEN,
+        space = Product::space(&attributes),
         seeds = [b"product", store.key().as_ref(), product_uuid.as_ref()],
         bump
     )]
     pub product: Account<'info, Product>,
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = CatalogIndex::LEN,
+        seeds = [b"catalog_index", store.key().as_ref()],
+        bump
+    )]
+    pub catalog_index: Account<'info, CatalogIndex>,
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = CatalogPage::LEN,
+        seeds = [
+            b"catalog_page",
+            store.key().as_ref(),
+            &catalog_index.append_page(1).to_le_bytes()
+        ],
+        bump
+    )]
+    pub catalog_page: Account<'info, CatalogPage>,
+    /// Required when the product is registered with a barcode
+    #[account(
+        init,
+        payer = authority,
+        space = SkuEntry::LEN,
+        seeds = [b"sku", store.key().as_ref(), SkuEntry::seed(barcode.as_deref())?],
+        bump
+    )]
+    pub sku_entry: Option<Account<'info, SkuEntry>>,
     #[account(mut)]
     pub authority: Signer<'info>,
     pub system_program: Program<'info, System>,
@@ -60,42 +267,302 @@ pub struct RegisterProduct<'info> {
 pub struct UpdateProduct<'info> {
     #[account(mut)]
     pub store: Account<'info, Store>,
+    #[account(
+        mut,
+        seeds = [b"audit_log", store.key().as_ref()],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Account<'info, AuditLog>,
+    // Permissions depend on which fields change and are checked in the handler
     #[account(
         mut,
         seeds = [b"product", store.key().as_ref(), product_uuid.as_ref()],
         bump,
-        has_one = store,
-        has_one = authority
+        has_one = store
     )]
     pub product: Account<'info, Product>,
+    /// Pays for (or is refunded) the rent when the attributes are resized
+    #[account(mut)]
     pub authority: Signer<'info>,
+    /// Present when `authority` is a POS session key rather than store staff
+    #[account(
+        mut,
+        seeds = [b"pos_session", store.key().as_ref(), authority.key().as_ref()],
+        bump = pos_session.bump
+    )]
+    pub pos_session: Option<Account<'info, PosSession>>,
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
 #[instruction(product_uuid: [u8; 16])]
 pub struct DeactivateProduct<'info> {
+    #[account(
+        mut,
+        constraint = store.has_permission(&authority.key(), StorePermission::ManageProducts) @ CustomError::Unauthorized
+    )]
+    pub store: Account<'info, Store>,
+    #[account(
+        mut,
+        seeds = [b"audit_log", store.key().as_ref()],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Account<'info, AuditLog>,
+    #[account(
+        mut,
+        seeds = [b"product", store.key().as_ref(), product_uuid.as_ref()],
+        bump,
+        has_one = store
+    )]
+    pub product: Account<'info, Product>,
+    pub authority: Signer<'info>,
+}
+
+// Product PDAs are passed in `remaining_accounts`, one per entry and in order,
+// each followed by its `SkuEntry` PDA when the entry has a barcode.
+// The whole batch goes on one catalog page.
+#[derive(Accounts)]
+#[instruction(entries: Vec<ProductBatchEntry>)]
+pub struct BatchRegisterProducts<'info> {
+    #[account(
+        constraint = store.is_active @ CustomError::StoreInactive,
+        constraint = store.has_permission(&authority.key(), StorePermission::ManageProducts) @ CustomError::Unauthorized
+    )]
+    pub store: Account<'info, Store>,
+    #[account(
+        mut,
+        seeds = [b"audit_log", store.key().as_ref()],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Account<'info, AuditLog>,
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = CatalogIndex::LEN,
+        seeds = [b"catalog_index", store.key().as_ref()],
+        bump
+    )]
+    pub catalog_index: Account<'info, CatalogIndex>,
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = CatalogPage::LEN,
+        seeds = [
+            b"catalog_page",
+            store.key().as_ref(),
+            &catalog_index.append_page(entries.len()).to_le_bytes()
+        ],
+        bump
+    )]
+    pub catalog_page: Account<'info, CatalogPage>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+// Bulk catalog edits, including stock, require ManageProducts
+#[derive(Accounts)]
+pub struct BatchUpdateProducts<'info> {
+    #[account(
+        constraint = store.has_permission(&authority.key(), StorePermission::ManageProducts) @ CustomError::Unauthorized
+    )]
+    pub store: Account<'info, Store>,
+    #[account(
+        mut,
+        seeds = [b"audit_log", store.key().as_ref()],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Account<'info, AuditLog>,
+    /// Pays for (or is refunded) the rent when attributes are resized
     #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+// Closes a product and drops it from the catalog page it was listed on. Its
+// variants are passed in `remaining_accounts` and closed with it.
+#[derive(Accounts)]
+#[instruction(product_uuid: [u8; 16], page_index: u32)]
+pub struct CloseProduct<'info> {
+    #[account(
+        constraint = store.has_permission(&authority.key(), StorePermission::ManageProducts) @ CustomError::Unauthorized
+    )]
     pub store: Account<'info, Store>,
+    #[account(
+        mut,
+        seeds = [b"audit_log", store.key().as_ref()],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Account<'info, AuditLog>,
     #[account(
         mut,
         seeds = [b"product", store.key().as_ref(), product_uuid.as_ref()],
         bump,
         has_one = store,
-        has_one = authority
+        close = authority
     )]
     pub product: Account<'info, Product>,
+    #[account(
+        mut,
+        seeds = [b"catalog_index", store.key().as_ref()],
+        bump = catalog_index.bump
+    )]
+    pub catalog_index: Account<'info, CatalogIndex>,
+    #[account(
+        mut,
+        seeds = [b"catalog_page", store.key().as_ref(), &page_index.to_le_bytes()],
+        bump = catalog_page.bump
+    )]
+    pub catalog_page: Account<'info, CatalogPage>,
+    /// The product's current barcode entry, required when it has one
+    #[account(
+        mut,
+        seeds = [b"sku", store.key().as_ref(), product.barcode.as_deref().unwrap_or_default().as_bytes()],
+        bump = sku_entry.bump,
+        close = authority
+    )]
+    pub sku_entry: Option<Account<'info, SkuEntry>>,
+    #[account(mut)]
     pub authority: Signer<'info>,
 }
 
+// Moves a product to a new barcode, or clears it, replacing its `SkuEntry`
 #[derive(Accounts)]
-pub struct PurchaseCart<'info> {
+#[instruction(product_uuid: [u8; 16], barcode: Option<String>)]
+pub struct SetProductBarcode<'info> {
+    #[account(
+        constraint = store.has_permission(&authority.key(), StorePermission::ManageProducts) @ CustomError::Unauthorized
+    )]
+    pub store: Account<'info, Store>,
+    #[account(
+        mut,
+        seeds = [b"audit_log", store.key().as_ref()],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Account<'info, AuditLog>,
+    #[account(
+        mut,
+        seeds = [b"product", store.key().as_ref(), product_uuid.as_ref()],
+        bump,
+        has_one = store
+    )]
+    pub product: Account<'info, Product>,
+    /// The product's current barcode entry, required when it has one
+    #[account(
+        mut,
+        seeds = [b"sku", store.key().as_ref(), product.barcode.as_deref().unwrap_or_default().as_bytes()],
+        bump = old_sku_entry.bump,
+        close = authority
+    )]
+    pub old_sku_entry: Option<Account<'info, SkuEntry>>,
+    /// Required when a new barcode is set
+    #[account(
+        init,
+        payer = authority,
+        space = SkuEntry::LEN,
+        seeds = [b"sku", store.key().as_ref(), SkuEntry::seed(barcode.as_deref())?],
+        bump
+    )]
+    pub new_sku_entry: Option<Account<'info, SkuEntry>>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(product_uuid: [u8; 16], variant_id: [u8; 16])]
+pub struct RegisterVariant<'info> {
+    #[account(
+        constraint = store.is_active @ CustomError::StoreInactive,
+        constraint = store.has_permission(&authority.key(), StorePermission::ManageProducts) @ CustomError::Unauthorized
+    )]
+    pub store: Account<'info, Store>,
+    #[account(
+        mut,
+        seeds = [b"audit_log", store.key().as_ref()],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Account<'info, AuditLog>,
+    #[account(
+        mut,
+        seeds = [b"product", store.key().as_ref(), product_uuid.as_ref()],
+        bump,
+        has_one = store
+    )]
+    pub product: Account<'info, Product>,
+    #[account(
+        init,
+        payer = authority,
+        space = ProductVariant::LEN,
+        seeds = [b"variant", product.key().as_ref(), variant_id.as_ref()],
+        bump
+    )]
+    pub variant: Account<'info, ProductVariant>,
     #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(product_uuid: [u8; 16], variant_id: [u8; 16])]
+pub struct UpdateVariant<'info> {
     pub store: Account<'info, Store>,
+    #[account(
+        mut,
+        seeds = [b"audit_log", store.key().as_ref()],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Account<'info, AuditLog>,
+    #[account(
+        seeds = [b"product", store.key().as_ref(), product_uuid.as_ref()],
+        bump,
+        has_one = store
+    )]
+    pub product: Account<'info, Product>,
+    // Permissions depend on which fields change and are checked in the handler
+    #[account(
+        mut,
+        seeds = [b"variant", product.key().as_ref(), variant_id.as_ref()],
+        bump = variant.bump,
+        has_one = product
+    )]
+    pub variant: Account<'info, ProductVariant>,
+    pub authority: Signer<'info>,
+    /// Present when `authority` is a POS session key rather than store staff
+    #[account(
+        mut,
+        seeds = [b"pos_session", store.key().as_ref(), authority.key().as_ref()],
+        bump = pos_session.bump
+    )]
+    pub pos_session: Option<Account<'info, PosSession>>,
+}
+
+#[derive(Accounts)]
+pub struct PurchaseCart<'info> {
+    #[account(
+        mut,
+        constraint = store.is_active @ CustomError::StoreInactive,
+        constraint = !store.is_suspended() @ CustomError::StoreSuspended
+    )]
+    pub store: Account<'info, Store>,
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        space = OrderCounter::LEN,
+        seeds = [b"order_counter", store.key().as_ref(), buyer.key().as_ref()],
+        bump
+    )]
+    pub order_counter: Account<'info, OrderCounter>,
     #[account(
         init,
         payer = buyer,
         space = Purchase::LEN,
-        seeds = [b"purchase", store.key().as_ref(), buyer.key().as_ref()],
+        seeds = [
+            b"purchase",
+            store.key().as_ref(),
+            buyer.key().as_ref(),
+            &order_counter.order_count.to_le_bytes()
+        ],
         bump
     )]
     pub receipt: Account<'info, Purchase>,
@@ -111,11 +578,25 @@ pub struct PurchaseCart<'info> {
     pub store_owner: AccountInfo<'info>,
     /// The escrow account that holds funds during the purchase
     #[account(
-        mut,
+        init_if_needed,
+        payer = buyer,
+        space = Escrow::LEN,
         seeds = [b"escrow", store.key().as_ref()],
         bump
     )]
     pub escrow_account: Account<'info, Escrow>,
+    /// Loyalty accounts, only needed when the store has a loyalty mint
+    #[account(
+        mut,
+        seeds = [b"loyalty_mint", store.key().as_ref()],
+        bump = loyalty_mint_info.bump,
+    )]
+    pub loyalty_mint_info: Option<Account<'info, LoyaltyMint>>,
+    #[account(mut)]
+    pub token_mint: Option<Account<'info, Mint>>,
+    #[account(mut)]
+    pub token_account: Option<Account<'info, TokenAccount>>,
+    pub token_program: Option<Program<'info, Token>>,
     pub system_program: Program<'info, System>,
 }
 /// off‑chain log
@@ -124,8 +605,60 @@ pub struct CartPurchased {
     pub store_id: Pubkey,
     pub buyer_id: Pubkey,
     pub product_uuids: Vec<[u8; 16]>,
+    pub variant_ids: Vec<Option<[u8; 16]>>,
     pub quantities: Vec<u64>,
     pub total_paid: u64,
     pub gas_fee: u64,
+    pub tip: u64,
+    pub timestamp: i64,
+    pub order_id: u64,
+}
+
+// Emitted for each batch entry that was skipped; `error_code` is the program
+// error the entry failed with
+#[event]
+pub struct ProductBatchItemFailed {
+    pub store: Pubkey,
+    pub index: u16,
+    pub product_uuid: [u8; 16],
+    pub error_code: u32,
+}
+
+#[event]
+pub struct ProductBatchProcessed {
+    pub store: Pubkey,
+    pub authority: Pubkey,
+    pub succeeded: u16,
+    pub failed: u16,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PurchaseCompleted {
+    pub store: Pubkey,
+    pub buyer: Pubkey,
+    pub total_amount: u64,
     pub timestamp: i64,
+    pub loyalty_points_earned: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sku_seed_accepts_barcodes_up_to_the_seed_limit() {
+        let barcode = "9".repeat(SkuEntry::MAX_BARCODE_LEN);
+        assert_eq!(SkuEntry::seed(Some(&barcode)).unwrap(), barcode.as_bytes());
+        assert_eq!(SkuEntry::seed(None).unwrap(), b"");
+    }
+
+    #[test]
+    fn sku_seed_rejects_oversized_barcodes() {
+        let barcode = "9".repeat(SkuEntry::MAX_BARCODE_LEN + 1);
+        assert_eq!(
+            SkuEntry::seed(Some(&barcode)).unwrap_err(),
+            CustomError::InvalidBarcode.into()
+        );
+    }
 }
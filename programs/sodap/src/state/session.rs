@@ -0,0 +1,209 @@
+use super::audit::AuditLog;
+use super::store::Store;
+use crate::error::CustomError;
+use crate::types::StorePermission;
+use anchor_lang::prelude::*;
+
+// Ephemeral signing key for a POS terminal, limited to a few store permissions
+// and a daily amount cap
+#[account]
+pub struct PosSession {
+    pub store: Pubkey,
+    pub session_key: Pubkey,
+    pub label: String,
+    pub permissions: u16,
+    pub daily_cap: u64,
+    pub spent_today: u64,
+    /// Day number (unix timestamp / 86400) that `spent_today` belongs to
+    pub current_day: i64,
+    pub expires_at: i64,
+    pub created_by: Pubkey,
+    /// `Store::session_epoch` at creation; the session is void once it changes
+    pub epoch: u64,
+    pub bump: u8,
+}
+
+impl PosSession {
+    pub const MAX_LABEL_LEN: usize = 32;
+    pub const MAX_DURATION: i64 = 7 * 86_400;
+    pub const LEN: usize = 8 + 32 + 32 + (4 + Self::MAX_LABEL_LEN) + 2 + 8 + 8 + 8 + 8 + 32 + 8 + 1;
+
+    /// Permissions a store may hand to a terminal; releasing escrow, catalog
+    /// and staff management stay with store admins
+    pub const ALLOWED_PERMISSIONS: u16 = StorePermission::AdjustStock.bit()
+        | StorePermission::IssueRefunds.bit()
+        | StorePermission::MintLoyalty.bit();
+
+    pub fn allows(&self, permission: StorePermission, now: i64) -> bool {
+        now < self.expires_at && self.permissions & permission.bit() != 0
+    }
+
+    /// Counts `amount` against today's cap, starting a fresh day when needed
+    pub fn charge(&mut self, amount: u64, now: i64) -> Result<()> {
+        let day = now / 86_400;
+        if day != self.current_day {
+            self.current_day = day;
+            self.spent_today = 0;
+        }
+        let spent = self
+            .spent_today
+            .checked_add(amount)
+            .ok_or(CustomError::ArithmeticError)?;
+        require!(spent <= self.daily_cap, CustomError::SessionLimitExceeded);
+        self.spent_today = spent;
+        Ok(())
+    }
+}
+
+/// Checks that `authority` may use `permission` on the store, either as store
+/// staff or through an unexpired POS session. Session use of amount-bearing
+/// actions is charged against the session's daily cap.
+pub fn authorize_staff_or_session(
+    store: &Store,
+    authority: &Pubkey,
+    session: Option<&mut Account<PosSession>>,
+    permission: StorePermission,
+    amount: u64,
+) -> Result<()> {
+    if store.has_permission(authority, permission) {
+        return Ok(());
+    }
+    let session = session.ok_or(CustomError::Unauthorized)?;
+    let now = Clock::get()?.unix_timestamp;
+    require!(
+        session.session_key == *authority
+            && session.epoch == store.session_epoch
+            && session.allows(permission, now),
+        CustomError::Unauthorized
+    );
+    session.charge(amount, now)
+}
+
+#[event]
+pub struct PosSessionCreated {
+    pub store: Pubkey,
+    pub session_key: Pubkey,
+    pub label: String,
+    pub permissions: u16,
+    pub daily_cap: u64,
+    pub expires_at: i64,
+}
+
+#[event]
+pub struct PosSessionRevoked {
+    pub store: Pubkey,
+    pub session_key: Pubkey,
+    pub revoked_at: i64,
+}
+
+#[derive(Accounts)]
+#[instruction(session_key: Pubkey)]
+pub struct CreatePosSession<'info> {
+    #[account(has_one = owner)]
+    pub store: Account<'info, Store>,
+    #[account(
+        mut,
+        seeds = [b"audit_log", store.key().as_ref()],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Account<'info, AuditLog>,
+    #[account(
+        init,
+        payer = owner,
+        space = PosSession::LEN,
+        seeds = [b"pos_session", store.key().as_ref(), session_key.as_ref()],
+        bump
+    )]
+    pub pos_session: Account<'info, PosSession>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(session_key: Pubkey)]
+pub struct RevokePosSession<'info> {
+    #[account(has_one = owner)]
+    pub store: Account<'info, Store>,
+    #[account(
+        mut,
+        seeds = [b"audit_log", store.key().as_ref()],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Account<'info, AuditLog>,
+    #[account(
+        mut,
+        seeds = [b"pos_session", store.key().as_ref(), session_key.as_ref()],
+        bump = pos_session.bump,
+        close = owner
+    )]
+    pub pos_session: Account<'info, PosSession>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: i64 = 86_400;
+
+    fn session(daily_cap: u64, now: i64) -> PosSession {
+        PosSession {
+            store: Pubkey::new_unique(),
+            session_key: Pubkey::new_unique(),
+            label: "till 1".to_string(),
+            permissions: StorePermission::IssueRefunds.bit(),
+            daily_cap,
+            spent_today: 0,
+            current_day: now / DAY,
+            expires_at: now + DAY,
+            created_by: Pubkey::new_unique(),
+            epoch: 0,
+            bump: 255,
+        }
+    }
+
+    #[test]
+    fn charge_accumulates_up_to_the_cap() {
+        let now = 100 * DAY + 10;
+        let mut s = session(1_000, now);
+        s.charge(600, now).unwrap();
+        s.charge(400, now + 60).unwrap();
+        assert_eq!(s.spent_today, 1_000);
+        assert_eq!(
+            s.charge(1, now + 120).unwrap_err(),
+            CustomError::SessionLimitExceeded.into()
+        );
+        assert_eq!(s.spent_today, 1_000);
+    }
+
+    #[test]
+    fn charge_resets_on_a_new_day() {
+        let now = 100 * DAY + DAY - 1;
+        let mut s = session(1_000, now);
+        s.charge(1_000, now).unwrap();
+        s.charge(700, now + 1).unwrap();
+        assert_eq!(s.current_day, 101);
+        assert_eq!(s.spent_today, 700);
+    }
+
+    #[test]
+    fn charge_over_the_cap_on_a_new_day_still_fails() {
+        let now = 100 * DAY;
+        let mut s = session(500, now);
+        s.charge(500, now).unwrap();
+        assert!(s.charge(501, now + DAY).is_err());
+        s.charge(500, now + DAY).unwrap();
+        assert_eq!(s.spent_today, 500);
+    }
+
+    #[test]
+    fn allows_checks_permission_and_expiry() {
+        let now = 100 * DAY;
+        let s = session(0, now);
+        assert!(s.allows(StorePermission::IssueRefunds, now));
+        assert!(!s.allows(StorePermission::AdjustStock, now));
+        assert!(!s.allows(StorePermission::IssueRefunds, s.expires_at));
+    }
+}
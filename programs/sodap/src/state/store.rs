@@ -1,5 +1,8 @@
-use crate::types::AdminRoleType;
+use super::admin::PlatformAdmin;
+use super::audit::AuditLog;
+use crate::error::CustomError;
 use crate::types::LoyaltyConfig;
+use crate::types::{AdminRoleType, StorePermission, SuspensionReason};
 use anchor_lang::prelude::*;
 
 // Store events
@@ -15,6 +18,7 @@ pub struct StoreRegistered {
 pub struct StoreUpdated {
     pub store_id: Pubkey,
     pub updated_by: Pubkey,
+    pub is_active: bool,
     pub updated_at: i64,
 }
 
@@ -33,6 +37,87 @@ pub struct AdminRemoved {
     pub removed_at: i64,
 }
 
+#[event]
+pub struct StoreSuspended {
+    pub store_id: Pubkey,
+    pub reason: SuspensionReason,
+    pub note: String,
+    pub suspended_by: Pubkey,
+    pub suspended_at: i64,
+}
+
+#[event]
+pub struct StoreUnsuspended {
+    pub store_id: Pubkey,
+    pub unsuspended_by: Pubkey,
+    pub unsuspended_at: i64,
+}
+
+#[event]
+pub struct AdminRoleChanged {
+    pub store_id: Pubkey,
+    pub admin_pubkey: Pubkey,
+    pub previous_role: AdminRoleType,
+    pub new_role: AdminRoleType,
+    pub permissions: u16,
+    pub changed_by: Pubkey,
+    pub changed_at: i64,
+}
+
+#[event]
+pub struct GuardiansUpdated {
+    pub store_id: Pubkey,
+    pub guardians: Vec<Pubkey>,
+    pub threshold: u8,
+    pub recovery_delay: i64,
+    pub updated_at: i64,
+}
+
+#[event]
+pub struct OwnerRecoveryInitiated {
+    pub store_id: Pubkey,
+    pub new_owner: Pubkey,
+    pub initiator: Pubkey,
+    pub executable_at: i64,
+}
+
+#[event]
+pub struct OwnerRecoveryApproved {
+    pub store_id: Pubkey,
+    pub guardian: Pubkey,
+    pub approvals: u8,
+}
+
+#[event]
+pub struct OwnerRecoveryVetoed {
+    pub store_id: Pubkey,
+    pub new_owner: Pubkey,
+    pub vetoed_at: i64,
+}
+
+#[event]
+pub struct OwnershipTransferProposed {
+    pub store_id: Pubkey,
+    pub current_owner: Pubkey,
+    pub new_owner: Pubkey,
+    pub proposed_at: i64,
+}
+
+#[event]
+pub struct OwnershipTransferCancelled {
+    pub store_id: Pubkey,
+    pub cancelled_by: Pubkey,
+    pub cancelled_at: i64,
+}
+
+#[event]
+pub struct OwnershipTransferred {
+    pub store_id: Pubkey,
+    pub previous_owner: Pubkey,
+    pub new_owner: Pubkey,
+    pub transferred_at: i64,
+}
+
 // Store/admin accounts
 #[account]
 #[derive(Debug)]
@@ -45,17 +130,174 @@ pub struct Store {
     pub is_active: bool,
     pub revenue: u64,
     pub admin_roles: Vec<AdminRole>,
+    /// Set while a platform admin has the store suspended
+    pub suspension: Option<StoreSuspension>,
+    /// Guardians able to recover the store if the owner loses their key;
+    /// the account grows to fit when a set is configured
+    pub guardian_set: Option<GuardianSet>,
+    /// Bumped on every change of owner; POS sessions issued under an older
+    /// epoch stop working
+    pub session_epoch: u64,
 }
 
 impl Store {
-    pub const LEN: usize =
-        8 + 32 + (4 + 200) + (4 + 500) + (4 + 200) + 16 + 1 + 8 + (4 + (33 * 10));
+    // Initial allocation; the admin roster beyond 10 entries is grown on demand
+    pub const LEN: usize = 8
+        + 32
+        + (4 + 200)
+        + (4 + 500)
+        + (4 + 200)
+        + 16
+        + 1
+        + 8
+        + (4 + (AdminRole::LEN * 10))
+        + (1 + StoreSuspension::LEN)
+        + 8;
+
+    pub fn is_suspended(&self) -> bool {
+        self.suspension.is_some()
+    }
+
+    /// The unexpired grant held by `user`, if any
+    pub fn active_role(&self, user: &Pubkey, now: i64) -> Option<&AdminRole> {
+        self.admin_roles
+            .iter()
+            .find(|r| r.admin_pubkey == *user && !r.is_expired(now))
+    }
+
+    /// Whether `user` holds `permission`; the owner implicitly holds all of them
+    pub fn has_permission(&self, user: &Pubkey, permission: StorePermission) -> bool {
+        *user == self.owner
+            || self
+                .active_role(user, current_timestamp())
+                .is_some_and(|r| r.permissions & permission.bit() != 0)
+    }
+
+    /// Whether `user` holds `permission` through a grant made no later than
+    /// `time`; timestamps have one-second resolution, so a grant from the same
+    /// second still counts
+    pub fn held_permission_before(
+        &self,
+        user: &Pubkey,
+        permission: StorePermission,
+        time: i64,
+    ) -> bool {
+        self.has_permission(user, permission)
+            && self
+                .active_role(user, current_timestamp())
+                .is_some_and(|r| r.granted_at <= time)
+    }
+
+    /// Account size needed to hold the store as currently populated
+    pub fn required_space(&self) -> Result<usize> {
+        Ok(8 + self.try_to_vec()?.len())
+    }
+}
+
+// Lists every store a wallet owns; grown and shrunk as stores are added or transferred
+#[account]
+pub struct OwnerStoreIndex {
+    pub owner: Pubkey,
+    pub stores: Vec<Pubkey>,
+}
+
+impl OwnerStoreIndex {
+    pub fn space(store_count: usize) -> usize {
+        8 + 32 + 4 + 32 * store_count
+    }
 }
 
 #[derive(Debug, AnchorSerialize, AnchorDeserialize, Clone)]
 pub struct AdminRole {
     pub admin_pubkey: Pubkey,
     pub role_type: AdminRoleType,
+    pub permissions: u16,
+    /// Unix timestamp after which the grant no longer applies; `None` never expires
+    pub expires_at: Option<i64>,
+    /// When the grant was made or last gained permissions
+    pub granted_at: i64,
+}
+
+impl AdminRole {
+    pub const LEN: usize = 32 + 1 + 2 + (1 + 8) + 8;
+
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.expires_at.is_some_and(|t| now >= t)
+    }
+}
+
+/// Current cluster time; if the clock can't be read every time-limited grant
+/// is treated as expired
+fn current_timestamp() -> i64 {
+    Clock::get().map_or(i64::MAX, |c| c.unix_timestamp)
+}
+
+#[derive(Debug, AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct StoreSuspension {
+    pub reason: SuspensionReason,
+    pub note: String,
+    pub suspended_by: Pubkey,
+    pub suspended_at: i64,
+}
+
+impl StoreSuspension {
+    pub const MAX_NOTE_LEN: usize = 200;
+    pub const LEN: usize = 1 + (4 + Self::MAX_NOTE_LEN) + 32 + 8;
+}
+
+#[derive(Debug, AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct GuardianSet {
+    pub guardians: Vec<Pubkey>,
+    pub threshold: u8,
+    /// Seconds between a recovery being initiated and it becoming executable
+    pub recovery_delay: i64,
+}
+
+impl GuardianSet {
+    pub const MAX_GUARDIANS: usize = 7;
+    pub const MIN_RECOVERY_DELAY: i64 = 2 * 86_400;
+}
+
+// Pending guardian recovery of the store owner; the owner can veto it until
+// `executable_at`, after which anyone holding the new key can execute it
+#[account]
+pub struct OwnerRecovery {
+    pub store: Pubkey,
+    /// Owner the recovery was started against; it lapses if the store
+    /// changes hands in the meantime
+    pub current_owner: Pubkey,
+    pub new_owner: Pubkey,
+    pub initiator: Pubkey,
+    pub approvals: Vec<Pubkey>,
+    pub initiated_at: i64,
+    pub executable_at: i64,
+    pub bump: u8,
+}
+
+impl OwnerRecovery {
+    pub const LEN: usize =
+        8 + 32 + 32 + 32 + 32 + (4 + 32 * GuardianSet::MAX_GUARDIANS) + 8 + 8 + 1;
+
+    /// Approvals from wallets that are still guardians of the store
+    pub fn valid_approvals(&self, guardian_set: &GuardianSet) -> usize {
+        self.approvals
+            .iter()
+            .filter(|a| guardian_set.guardians.contains(a))
+            .count()
+    }
+}
+
+// Pending two-step ownership transfer, closed when accepted or cancelled
+#[account]
+pub struct OwnershipTransfer {
+    pub store: Pubkey,
+    pub current_owner: Pubkey,
+    pub new_owner: Pubkey,
+    pub proposed_at: i64,
+}
+
+impl OwnershipTransfer {
+    pub const LEN: usize = 8 + 32 + 32 + 32 + 8;
 }
 
 // Context structs for store/admin instructions
@@ -70,6 +312,22 @@ pub struct RegisterStore<'info> {
         bump
     )]
     pub store: Account<'info, Store>,
+    #[account(
+        init,
+        payer = authority,
+        space = AuditLog::LEN,
+        seeds = [b"audit_log", store.key().as_ref()],
+        bump
+    )]
+    pub audit_log: Account<'info, AuditLog>,
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = OwnerStoreIndex::space(0),
+        seeds = [b"owner_stores", authority.key().as_ref()],
+        bump
+    )]
+    pub owner_index: Account<'info, OwnerStoreIndex>,
     #[account(mut)]
     pub authority: Signer<'info>,
     pub system_program: Program<'info, System>,
@@ -85,6 +343,12 @@ pub struct UpdateStore<'info> {
         has_one = owner
     )]
     pub store: Account<'info, Store>,
+    #[account(
+        mut,
+        seeds = [b"audit_log", store.key().as_ref()],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Account<'info, AuditLog>,
     pub owner: Signer<'info>,
 }
 
@@ -95,10 +359,19 @@ pub struct AddAdmin<'info> {
         mut,
         seeds = [b"store", store_id.as_ref()],
         bump,
-        has_one = owner
+        constraint = store.has_permission(&authority.key(), StorePermission::ManageStaff) @ CustomError::Unauthorized
     )]
     pub store: Account<'info, Store>,
-    pub owner: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"audit_log", store.key().as_ref()],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Account<'info, AuditLog>,
+    /// Pays for (or is refunded) the rent when the admin roster is resized
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
@@ -108,15 +381,323 @@ pub struct RemoveAdmin<'info> {
         mut,
         seeds = [b"store", store_id.as_ref()],
         bump,
-        has_one = owner
+        constraint = store.has_permission(&authority.key(), StorePermission::ManageStaff) @ CustomError::Unauthorized
+    )]
+    pub store: Account<'info, Store>,
+    #[account(
+        mut,
+        seeds = [b"audit_log", store.key().as_ref()],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Account<'info, AuditLog>,
+    pub authority: Signer<'info>,
+    /// CHECK: Receives rent freed by shrinking the roster; must be the store owner
+    #[account(mut, address = store.owner @ CustomError::Unauthorized)]
+    pub store_owner: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(store_id: Pubkey)]
+pub struct ChangeAdminRole<'info> {
+    #[account(
+        mut,
+        seeds = [b"store", store_id.as_ref()],
+        bump,
+        constraint = store.has_permission(&authority.key(), StorePermission::ManageStaff) @ CustomError::Unauthorized
+    )]
+    pub store: Account<'info, Store>,
+    #[account(
+        mut,
+        seeds = [b"audit_log", store.key().as_ref()],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Account<'info, AuditLog>,
+    pub authority: Signer<'info>,
+}
+
+// Drop expired staff grants; anyone may call it, reclaimed rent goes to the owner
+#[derive(Accounts)]
+#[instruction(store_id: Pubkey)]
+pub struct PruneExpiredAdmins<'info> {
+    #[account(
+        mut,
+        seeds = [b"store", store_id.as_ref()],
+        bump
     )]
     pub store: Account<'info, Store>,
+    #[account(
+        mut,
+        seeds = [b"audit_log", store.key().as_ref()],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Account<'info, AuditLog>,
+    /// CHECK: Receives rent freed by shrinking the roster; must be the store owner
+    #[account(mut, address = store.owner @ CustomError::Unauthorized)]
+    pub store_owner: AccountInfo<'info>,
+    /// Whoever runs the prune; recorded as the actor in the audit log
+    pub caller: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+// Pause or resume a store; callable by the store owner or a platform admin
+#[derive(Accounts)]
+pub struct SetStoreStatus<'info> {
+    #[account(mut)]
+    pub store: Account<'info, Store>,
+    #[account(
+        mut,
+        seeds = [b"audit_log", store.key().as_ref()],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Account<'info, AuditLog>,
+    #[account(
+        seeds = [b"platform_admin", authority.key().as_ref()],
+        bump = platform_admin.bump
+    )]
+    pub platform_admin: Option<Account<'info, PlatformAdmin>>,
+    pub authority: Signer<'info>,
+}
+
+// Suspend or lift a suspension; platform admins only, the owner can't override it
+#[derive(Accounts)]
+pub struct ModerateStore<'info> {
+    #[account(mut)]
+    pub store: Account<'info, Store>,
+    #[account(
+        mut,
+        seeds = [b"audit_log", store.key().as_ref()],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Account<'info, AuditLog>,
+    #[account(
+        seeds = [b"platform_admin", authority.key().as_ref()],
+        bump = platform_admin.bump,
+        constraint = platform_admin.is_active() @ CustomError::Unauthorized
+    )]
+    pub platform_admin: Account<'info, PlatformAdmin>,
+    /// Pays for growing the store account when the note doesn't fit
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ProposeOwnershipTransfer<'info> {
+    #[account(has_one = owner)]
+    pub store: Account<'info, Store>,
+    #[account(
+        mut,
+        seeds = [b"audit_log", store.key().as_ref()],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Account<'info, AuditLog>,
+    #[account(
+        init,
+        payer = owner,
+        space = OwnershipTransfer::LEN,
+        seeds = [b"ownership_transfer", store.key().as_ref()],
+        bump
+    )]
+    pub ownership_transfer: Account<'info, OwnershipTransfer>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptOwnershipTransfer<'info> {
+    #[account(mut)]
+    pub store: Account<'info, Store>,
+    #[account(
+        mut,
+        seeds = [b"audit_log", store.key().as_ref()],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Account<'info, AuditLog>,
+    #[account(
+        mut,
+        seeds = [b"ownership_transfer", store.key().as_ref()],
+        bump,
+        has_one = store,
+        has_one = new_owner,
+        constraint = ownership_transfer.current_owner == store.owner @ CustomError::Unauthorized,
+        close = previous_owner
+    )]
+    pub ownership_transfer: Account<'info, OwnershipTransfer>,
+    #[account(mut)]
+    pub new_owner: Signer<'info>,
+    /// CHECK: Receives the proposal rent back; must be the current store owner
+    #[account(mut, address = store.owner @ CustomError::Unauthorized)]
+    pub previous_owner: AccountInfo<'info>,
+    /// CHECK: The previous owner's store index PDA; left uninitialized for
+    /// stores registered before owner indexes existed
+    #[account(
+        mut,
+        seeds = [b"owner_stores", previous_owner.key().as_ref()],
+        bump
+    )]
+    pub previous_owner_index: UncheckedAccount<'info>,
+    #[account(
+        init_if_needed,
+        payer = new_owner,
+        space = OwnerStoreIndex::space(0),
+        seeds = [b"owner_stores", new_owner.key().as_ref()],
+        bump
+    )]
+    pub new_owner_index: Account<'info, OwnerStoreIndex>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetGuardians<'info> {
+    #[account(mut, has_one = owner)]
+    pub store: Account<'info, Store>,
+    #[account(
+        mut,
+        seeds = [b"audit_log", store.key().as_ref()],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Account<'info, AuditLog>,
+    /// Pays for (or is refunded) the rent when the guardian set is resized
+    #[account(mut)]
     pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
 }
 
-pub fn has_role(store: &Store, user: &Pubkey, role: AdminRoleType) -> bool {
-    store
-        .admin_roles
-        .iter()
-        .any(|r| r.admin_pubkey == *user && r.role_type == role)
+#[derive(Accounts)]
+pub struct InitiateOwnerRecovery<'info> {
+    pub store: Account<'info, Store>,
+    #[account(
+        mut,
+        seeds = [b"audit_log", store.key().as_ref()],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Account<'info, AuditLog>,
+    #[account(
+        init,
+        payer = guardian,
+        space = OwnerRecovery::LEN,
+        seeds = [b"owner_recovery", store.key().as_ref()],
+        bump
+    )]
+    pub owner_recovery: Account<'info, OwnerRecovery>,
+    #[account(mut)]
+    pub guardian: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ApproveOwnerRecovery<'info> {
+    pub store: Account<'info, Store>,
+    #[account(
+        mut,
+        seeds = [b"audit_log", store.key().as_ref()],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Account<'info, AuditLog>,
+    #[account(
+        mut,
+        seeds = [b"owner_recovery", store.key().as_ref()],
+        bump = owner_recovery.bump,
+        has_one = store,
+        constraint = owner_recovery.current_owner == store.owner @ CustomError::Unauthorized
+    )]
+    pub owner_recovery: Account<'info, OwnerRecovery>,
+    pub guardian: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct VetoOwnerRecovery<'info> {
+    #[account(has_one = owner)]
+    pub store: Account<'info, Store>,
+    #[account(
+        mut,
+        seeds = [b"audit_log", store.key().as_ref()],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Account<'info, AuditLog>,
+    #[account(
+        mut,
+        seeds = [b"owner_recovery", store.key().as_ref()],
+        bump = owner_recovery.bump,
+        has_one = store,
+        has_one = initiator,
+        close = initiator
+    )]
+    pub owner_recovery: Account<'info, OwnerRecovery>,
+    /// CHECK: Guardian who paid for the recovery account and gets the rent back
+    #[account(mut)]
+    pub initiator: AccountInfo<'info>,
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteOwnerRecovery<'info> {
+    #[account(mut)]
+    pub store: Account<'info, Store>,
+    #[account(
+        mut,
+        seeds = [b"audit_log", store.key().as_ref()],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Account<'info, AuditLog>,
+    #[account(
+        mut,
+        seeds = [b"owner_recovery", store.key().as_ref()],
+        bump = owner_recovery.bump,
+        has_one = store,
+        has_one = new_owner,
+        has_one = initiator,
+        constraint = owner_recovery.current_owner == store.owner @ CustomError::Unauthorized,
+        close = initiator
+    )]
+    pub owner_recovery: Account<'info, OwnerRecovery>,
+    #[account(mut)]
+    pub new_owner: Signer<'info>,
+    /// CHECK: Guardian who paid for the recovery account and gets the rent back
+    #[account(mut)]
+    pub initiator: AccountInfo<'info>,
+    /// CHECK: The owner being replaced; receives rent freed from their index
+    #[account(mut, address = store.owner @ CustomError::Unauthorized)]
+    pub previous_owner: AccountInfo<'info>,
+    /// CHECK: The previous owner's store index PDA; left uninitialized for
+    /// stores registered before owner indexes existed
+    #[account(
+        mut,
+        seeds = [b"owner_stores", previous_owner.key().as_ref()],
+        bump
+    )]
+    pub previous_owner_index: UncheckedAccount<'info>,
+    #[account(
+        init_if_needed,
+        payer = new_owner,
+        space = OwnerStoreIndex::space(0),
+        seeds = [b"owner_stores", new_owner.key().as_ref()],
+        bump
+    )]
+    pub new_owner_index: Account<'info, OwnerStoreIndex>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CancelOwnershipTransfer<'info> {
+    #[account(has_one = owner)]
+    pub store: Account<'info, Store>,
+    #[account(
+        mut,
+        seeds = [b"audit_log", store.key().as_ref()],
+        bump = audit_log.bump
+    )]
+    pub audit_log: Account<'info, AuditLog>,
+    #[account(
+        mut,
+        seeds = [b"ownership_transfer", store.key().as_ref()],
+        bump,
+        has_one = store,
+        close = owner
+    )]
+    pub ownership_transfer: Account<'info, OwnershipTransfer>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
 }
@@ -1,3 +1,5 @@
+use super::store::Store;
+use crate::error::CustomError;
 use anchor_lang::prelude::*;
 #[event]
 pub struct UserProfileUpdated {
@@ -6,6 +8,17 @@ pub struct UserProfileUpdated {
     pub updated_at: i64,
 }
 
+// Lines of a scan resolved to product UUIDs, barcode lines following the
+// UUID lines
+#[event]
+pub struct ItemsScanned {
+    pub buyer: Pubkey,
+    pub store: Pubkey,
+    pub product_uuids: Vec<[u8; 16]>,
+    pub quantities: Vec<u64>,
+    pub timestamp: i64,
+}
+
 #[account]
 pub struct UserProfile {
     pub authority: Pubkey,
@@ -31,6 +44,7 @@ impl UserProfile {
         + 8;
 }
 
+#[allow(dead_code)]
 #[derive(AnchorSerialize, AnchorDeserialize, Clone)]
 pub struct PurchaseRecord {
     pub store_id: Pubkey,
@@ -57,6 +71,12 @@ pub struct CreateOrUpdateUserProfile<'info> {
 }
 
 #[derive(Accounts)]
+#[instruction(
+    product_uuids: Vec<[u8; 16]>,
+    barcodes: Vec<String>,
+    quantities: Vec<u64>,
+    store_id: Pubkey
+)]
 pub struct ScanAndPurchase<'info> {
     #[account(
         mut,
@@ -65,5 +85,12 @@ pub struct ScanAndPurchase<'info> {
         has_one = authority
     )]
     pub user_profile: Account<'info, UserProfile>,
+    #[account(
+        seeds = [b"store", store_id.as_ref()],
+        bump,
+        constraint = store.is_active @ CustomError::StoreInactive,
+        constraint = !store.is_suspended() @ CustomError::StoreSuspended
+    )]
+    pub store: Account<'info, Store>,
     pub authority: Signer<'info>,
 }
@@ -10,6 +10,56 @@ pub enum AdminRoleType {
     Viewer,
 }
 
+impl AdminRoleType {
+    /// Permissions granted when a role is assigned without an explicit set
+    pub fn default_permissions(&self) -> u16 {
+        match self {
+            AdminRoleType::Owner => StorePermission::ALL,
+            // Minting loyalty points can drain escrow through SOL redemption,
+            // so managers only get it when granted explicitly
+            AdminRoleType::Manager => {
+                StorePermission::ManageProducts.bit() | StorePermission::AdjustStock.bit()
+            }
+            AdminRoleType::Viewer => 0,
+        }
+    }
+}
+
+// Individual store permissions, stored as a bit set on each admin role
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq)]
+pub enum StorePermission {
+    ManageProducts,
+    AdjustStock,
+    ReleaseEscrow,
+    IssueRefunds,
+    MintLoyalty,
+    ManageStaff,
+}
+
+impl StorePermission {
+    pub const ALL: u16 = (1 << 6) - 1;
+
+    pub const fn bit(self) -> u16 {
+        1 << (self as u16)
+    }
+}
+
+// Why a platform admin suspended a store
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq)]
+pub enum SuspensionReason {
+    Fraud,
+    PolicyViolation,
+    CustomerComplaints,
+    LegalRequest,
+    Other,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq)]
+pub enum PlatformAdminStatus {
+    Active,
+    Suspended,
+}
+
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
 pub enum TokenizedType {
     Digital,
@@ -29,6 +79,7 @@ pub enum AnomalyFlag {
     Suspicious,
 }
 
+#[allow(dead_code)]
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
 pub enum MintStatus {
     NotMinted,
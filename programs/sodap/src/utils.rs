@@ -1,18 +1,49 @@
-// Helper functions (has_role, is_super_root_admin, check_root_password, is_platform_admin, etc.) will be placed here.
+// Helper functions shared by the instruction handlers.
+use crate::error::CustomError;
 use anchor_lang::prelude::*;
+use anchor_lang::system_program;
 
-// Utility functions
-pub fn check_root_password(
-    username: &str,
-    password: &str,
-    admin_username: &str,
-    admin_password: &str,
-) -> bool {
-    // This is just a placeholder - in production you would use proper auth
-    username == admin_username && password == admin_password
+// Moves lamports out of a program-owned PDA such as the store escrow. The
+// system program cannot debit accounts that carry data, so the balance is
+// adjusted directly, never dipping into the rent-exempt reserve.
+pub fn withdraw_lamports(from: &AccountInfo, to: &AccountInfo, amount: u64) -> Result<()> {
+    let rent_reserve = Rent::get()?.minimum_balance(from.data_len());
+    let available = from.lamports().saturating_sub(rent_reserve);
+    require!(amount <= available, CustomError::InsufficientEscrowBalance);
+
+    **from.try_borrow_mut_lamports()? -= amount;
+    **to.try_borrow_mut_lamports()? = to
+        .lamports()
+        .checked_add(amount)
+        .ok_or(CustomError::ArithmeticError)?;
+    Ok(())
 }
 
-pub fn is_super_root_admin(key: &Pubkey, super_admin_pubkey: &Pubkey) -> bool {
-    // Check if the key matches the super admin key
-    key == super_admin_pubkey
+// Resizes a program-owned account, topping up rent from `payer` when growing
+// and returning the surplus to `payer` when shrinking.
+pub fn resize_account<'info>(
+    account: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    new_len: usize,
+) -> Result<()> {
+    account.realloc(new_len, false)?;
+
+    let required = Rent::get()?.minimum_balance(new_len);
+    let current = account.lamports();
+    if required > current {
+        system_program::transfer(
+            CpiContext::new(
+                system_program.clone(),
+                system_program::Transfer {
+                    from: payer.clone(),
+                    to: account.clone(),
+                },
+            ),
+            required - current,
+        )?;
+    } else if current > required {
+        withdraw_lamports(account, payer, current - required)?;
+    }
+    Ok(())
 }
@@ -0,0 +1,83 @@
+// @ts-nocheck - Disable TypeScript checking for this file due to Anchor-generated type mismatches
+
+import { SystemProgram, Keypair } from "@solana/web3.js";
+import { BN } from "@coral-xyz/anchor";
+import { assert } from "chai";
+import {
+  program,
+  requestAirdrop,
+  clusterTime,
+  waitForClusterTime,
+  storeFixture,
+  latestEntry,
+} from "./helpers";
+
+// Store audit log: privileged store actions land in the per-store ring buffer
+describe("sodap audit log", () => {
+  const fx = storeFixture();
+  const admin = Keypair.generate();
+
+  async function addAdmin(adminKey, role, expiresAt = null) {
+    await program.methods
+      .addStoreAdmin(fx.storeId, adminKey, role, null, expiresAt)
+      .accounts({
+        store: fx.store,
+        auditLog: fx.auditLog,
+        authority: fx.owner.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([fx.owner])
+      .rpc();
+  }
+
+  before(async () => {
+    await fx.register("Audit Store");
+  });
+
+  it("records adding an admin", async () => {
+    const before = await program.account.auditLog.fetch(fx.auditLog);
+
+    await addAdmin(admin.publicKey, { manager: {} });
+
+    const log = await program.account.auditLog.fetch(fx.auditLog);
+    assert.equal(log.totalEntries.toNumber(), before.totalEntries.toNumber() + 1);
+
+    const entry = latestEntry(log);
+    assert.ok(entry.actor.equals(fx.owner.publicKey));
+    assert.ok(entry.target.equals(admin.publicKey));
+    assert.deepEqual(entry.action, { adminAdded: {} });
+  });
+
+  it("records each expired grant removed by a prune, with the caller as actor", async () => {
+    const expiring = Keypair.generate();
+    const caller = Keypair.generate();
+    await requestAirdrop(caller.publicKey);
+
+    // Expiry is set against the cluster clock, which the program checks
+    const expiresAt = (await clusterTime()) + 5;
+    await addAdmin(expiring.publicKey, { viewer: {} }, new BN(expiresAt));
+    await waitForClusterTime(expiresAt);
+
+    await program.methods
+      .pruneExpiredAdmins(fx.storeId)
+      .accounts({
+        store: fx.store,
+        auditLog: fx.auditLog,
+        storeOwner: fx.owner.publicKey,
+        caller: caller.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([caller])
+      .rpc();
+
+    const store = await program.account.store.fetch(fx.store);
+    assert.isFalse(
+      store.adminRoles.some((r) => r.adminPubkey.equals(expiring.publicKey))
+    );
+
+    const entry = latestEntry(await program.account.auditLog.fetch(fx.auditLog));
+    assert.deepEqual(entry.action, { adminRemoved: {} });
+    assert.ok(entry.actor.equals(caller.publicKey));
+    assert.ok(entry.target.equals(expiring.publicKey));
+  });
+});
@@ -0,0 +1,153 @@
+// @ts-nocheck - Disable TypeScript checking for this file due to Anchor-generated type mismatches
+
+import { Keypair } from "@solana/web3.js";
+import { BN } from "@coral-xyz/anchor";
+import { assert } from "chai";
+import { provider, program, randomUuid, storeFixture } from "./helpers";
+
+// Barcode lookup: SkuEntry PDAs map a store's barcodes to its products
+describe("sodap barcodes", () => {
+  const fx = storeFixture();
+
+  function batchEntry(uuid: number[], barcode: string | null) {
+    return {
+      productUuid: uuid,
+      price: new BN(500_000),
+      stock: new BN(5),
+      tokenizedType: { physical: {} },
+      metadataUri: "https://example.com/product.json",
+      attributes: [],
+      barcode,
+    };
+  }
+
+  before(async () => {
+    await fx.register("Barcode Store");
+  });
+
+  it("maps a registered barcode to its product", async () => {
+    const uuid = randomUuid();
+    const barcode = "4006381333931";
+    await fx.registerProduct(uuid, 1_000_000, 10, barcode);
+
+    const sku = await program.account.skuEntry.fetch(fx.sku(barcode));
+    assert.equal(sku.barcode, barcode);
+    assert.deepEqual(Array.from(sku.productUuid), uuid);
+  });
+
+  it("frees a closed product's barcode", async () => {
+    const uuid = randomUuid();
+    const barcode = "4006381333948";
+    await fx.registerProduct(uuid, 1_000_000, 10, barcode);
+
+    await program.methods
+      .closeProduct(uuid, 0)
+      .accounts({
+        store: fx.store,
+        auditLog: fx.auditLog,
+        product: fx.product(uuid),
+        catalogIndex: fx.catalogIndex,
+        catalogPage: fx.catalogPage(0),
+        skuEntry: fx.sku(barcode),
+        authority: fx.owner.publicKey,
+      })
+      .signers([fx.owner])
+      .rpc();
+
+    assert.isNull(await provider.connection.getAccountInfo(fx.sku(barcode)));
+  });
+
+  it("rejects a barcode longer than a PDA seed with InvalidBarcode", async () => {
+    const uuid = randomUuid();
+    const barcode = "9".repeat(33);
+    // Any address will do: the length is checked before the PDA is derived
+    const skuEntry = Keypair.generate().publicKey;
+
+    try {
+      await program.methods
+        .registerProduct(
+          uuid,
+          new BN(1_000_000),
+          new BN(10),
+          { physical: {} },
+          "https://example.com/product.json",
+          [],
+          barcode
+        )
+        .accounts({
+          store: fx.store,
+          auditLog: fx.auditLog,
+          product: fx.product(uuid),
+          catalogIndex: fx.catalogIndex,
+          catalogPage: fx.catalogPage(0),
+          skuEntry,
+          authority: fx.owner.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([fx.owner])
+        .rpc();
+      assert.fail("oversized barcode should be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "InvalidBarcode");
+    }
+  });
+
+  it("registers barcodes for products created in a batch", async () => {
+    const barcodedUuid = randomUuid();
+    const plainUuid = randomUuid();
+    const barcode = "5012345678900";
+
+    await program.methods
+      .batchRegisterProducts([batchEntry(barcodedUuid, barcode), batchEntry(plainUuid, null)])
+      .accounts({
+        store: fx.store,
+        auditLog: fx.auditLog,
+        catalogIndex: fx.catalogIndex,
+        catalogPage: fx.catalogPage(0),
+        authority: fx.owner.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .remainingAccounts([
+        { pubkey: fx.product(barcodedUuid), isSigner: false, isWritable: true },
+        { pubkey: fx.sku(barcode), isSigner: false, isWritable: true },
+        { pubkey: fx.product(plainUuid), isSigner: false, isWritable: true },
+      ])
+      .signers([fx.owner])
+      .rpc();
+
+    const sku = await program.account.skuEntry.fetch(fx.sku(barcode));
+    assert.equal(sku.barcode, barcode);
+    assert.deepEqual(Array.from(sku.productUuid), barcodedUuid);
+
+    const product = await program.account.product.fetch(fx.product(barcodedUuid));
+    assert.equal(product.barcode, barcode);
+
+    const plain = await program.account.product.fetch(fx.product(plainUuid));
+    assert.isNull(plain.barcode);
+  });
+
+  it("skips a batch entry whose barcode is already taken", async () => {
+    const uuid = randomUuid();
+    const barcode = "5012345678900";
+
+    await program.methods
+      .batchRegisterProducts([batchEntry(uuid, barcode)])
+      .accounts({
+        store: fx.store,
+        auditLog: fx.auditLog,
+        catalogIndex: fx.catalogIndex,
+        catalogPage: fx.catalogPage(0),
+        authority: fx.owner.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .remainingAccounts([
+        { pubkey: fx.product(uuid), isSigner: false, isWritable: true },
+        { pubkey: fx.sku(barcode), isSigner: false, isWritable: true },
+      ])
+      .signers([fx.owner])
+      .rpc();
+
+    // The failed entry leaves no product behind
+    assert.isNull(await provider.connection.getAccountInfo(fx.product(uuid)));
+  });
+});
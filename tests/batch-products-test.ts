@@ -0,0 +1,63 @@
+// @ts-nocheck - Disable TypeScript checking for this file due to Anchor-generated type mismatches
+
+import { SystemProgram, Transaction } from "@solana/web3.js";
+import { BN } from "@coral-xyz/anchor";
+import { assert } from "chai";
+import { provider, program, randomUuid, storeFixture } from "./helpers";
+
+// Batch registration: products created from remaining accounts in one transaction
+describe("sodap batch product registration", () => {
+  const fx = storeFixture();
+
+  function batchEntry(uuid: number[]) {
+    return {
+      productUuid: uuid,
+      price: new BN(500_000),
+      stock: new BN(5),
+      tokenizedType: { physical: {} },
+      metadataUri: "https://example.com/product.json",
+      attributes: [],
+      barcode: null,
+    };
+  }
+
+  before(async () => {
+    await fx.register("Batch Store");
+  });
+
+  it("creates a product whose address was pre-funded", async () => {
+    const uuid = randomUuid();
+
+    // Anyone can send lamports to the predictable product PDA
+    await provider.sendAndConfirm(
+      new Transaction().add(
+        SystemProgram.transfer({
+          fromPubkey: provider.wallet.publicKey,
+          toPubkey: fx.product(uuid),
+          lamports: 1,
+        })
+      )
+    );
+
+    await program.methods
+      .batchRegisterProducts([batchEntry(uuid)])
+      .accounts({
+        store: fx.store,
+        auditLog: fx.auditLog,
+        catalogIndex: fx.catalogIndex,
+        catalogPage: fx.catalogPage(0),
+        authority: fx.owner.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .remainingAccounts([{ pubkey: fx.product(uuid), isSigner: false, isWritable: true }])
+      .signers([fx.owner])
+      .rpc();
+
+    const product = await program.account.product.fetch(fx.product(uuid));
+    assert.deepEqual(Array.from(product.uuid), uuid);
+    assert.equal(product.price.toNumber(), 500_000);
+
+    const info = await provider.connection.getAccountInfo(fx.product(uuid));
+    assert.ok(info.owner.equals(program.programId));
+  });
+});
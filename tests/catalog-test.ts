@@ -0,0 +1,114 @@
+// @ts-nocheck - Disable TypeScript checking for this file due to Anchor-generated type mismatches
+
+import { SystemProgram } from "@solana/web3.js";
+import { BN } from "@coral-xyz/anchor";
+import { assert } from "chai";
+import { provider, program, randomUuid, storeFixture } from "./helpers";
+
+// Store catalog: registered products are listed on paged CatalogPage accounts
+describe("sodap catalog", () => {
+  const fx = storeFixture();
+
+  const firstUuid = randomUuid();
+  const secondUuid = randomUuid();
+
+  before(async () => {
+    await fx.register("Catalog Store");
+  });
+
+  it("lists registered products on the first catalog page", async () => {
+    await fx.registerProduct(firstUuid);
+    await fx.registerProduct(secondUuid);
+
+    const index = await program.account.catalogIndex.fetch(fx.catalogIndex);
+    assert.equal(index.pageCount, 1);
+    assert.equal(index.lastPageLen, 2);
+    assert.equal(index.totalProducts.toNumber(), 2);
+
+    const page = await program.account.catalogPage.fetch(fx.catalogPage(0));
+    assert.equal(page.pageIndex, 0);
+    assert.ok(page.products[0].equals(fx.product(firstUuid)));
+    assert.ok(page.products[1].equals(fx.product(secondUuid)));
+  });
+
+  it("unlists a closed product", async () => {
+    await program.methods
+      .closeProduct(secondUuid, 0)
+      .accounts({
+        store: fx.store,
+        auditLog: fx.auditLog,
+        product: fx.product(secondUuid),
+        catalogIndex: fx.catalogIndex,
+        catalogPage: fx.catalogPage(0),
+        skuEntry: null,
+        authority: fx.owner.publicKey,
+      })
+      .signers([fx.owner])
+      .rpc();
+
+    const index = await program.account.catalogIndex.fetch(fx.catalogIndex);
+    assert.equal(index.lastPageLen, 1);
+    assert.equal(index.totalProducts.toNumber(), 1);
+
+    const page = await program.account.catalogPage.fetch(fx.catalogPage(0));
+    assert.equal(page.products.length, 1);
+    assert.ok(page.products[0].equals(fx.product(firstUuid)));
+
+    assert.isNull(await provider.connection.getAccountInfo(fx.product(secondUuid)));
+  });
+
+  it("closes a product's variants with it and requires all of them", async () => {
+    const uuid = randomUuid();
+    const variantIds = [randomUuid(), randomUuid()];
+    await fx.registerProduct(uuid);
+    for (const variantId of variantIds) {
+      await program.methods
+        .registerVariant(uuid, variantId, "SKU", [], new BN(1_000_000), new BN(5))
+        .accounts({
+          store: fx.store,
+          auditLog: fx.auditLog,
+          product: fx.product(uuid),
+          variant: fx.variant(uuid, variantId),
+          authority: fx.owner.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([fx.owner])
+        .rpc();
+    }
+    const product = await program.account.product.fetch(fx.product(uuid));
+    assert.equal(product.variantCount, 2);
+
+    const close = (variants) =>
+      program.methods
+        .closeProduct(uuid, 0)
+        .accounts({
+          store: fx.store,
+          auditLog: fx.auditLog,
+          product: fx.product(uuid),
+          catalogIndex: fx.catalogIndex,
+          catalogPage: fx.catalogPage(0),
+          skuEntry: null,
+          authority: fx.owner.publicKey,
+        })
+        .remainingAccounts(
+          variants.map((pubkey) => ({ pubkey, isSigner: false, isWritable: true }))
+        )
+        .signers([fx.owner])
+        .rpc();
+
+    // Leaving a variant out would strand it at its address
+    try {
+      await close([fx.variant(uuid, variantIds[0])]);
+      assert.fail("closing without every variant should be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "InvalidParameters");
+    }
+
+    await close(variantIds.map((variantId) => fx.variant(uuid, variantId)));
+
+    assert.isNull(await provider.connection.getAccountInfo(fx.product(uuid)));
+    for (const variantId of variantIds) {
+      assert.isNull(await provider.connection.getAccountInfo(fx.variant(uuid, variantId)));
+    }
+  });
+});
@@ -0,0 +1,176 @@
+// @ts-nocheck - Disable TypeScript checking for this file due to Anchor-generated type mismatches
+
+// Shared fixtures for the store-scoped test suites: PDA derivation, funding,
+// store registration and the cluster clock
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { Sodap } from "../target/types/sodap";
+import { PublicKey, SystemProgram, Keypair } from "@solana/web3.js";
+import { BN } from "@coral-xyz/anchor";
+
+export const provider = anchor.AnchorProvider.env();
+anchor.setProvider(provider);
+
+export const program = anchor.workspace.Sodap as Program<Sodap>;
+
+function pda(seeds: Buffer[]) {
+  return PublicKey.findProgramAddressSync(seeds, program.programId)[0];
+}
+
+export function u32Seed(value: number) {
+  const seed = Buffer.alloc(4);
+  seed.writeUInt32LE(value);
+  return seed;
+}
+
+export function u64Seed(value: number) {
+  return new BN(value).toArrayLike(Buffer, "le", 8);
+}
+
+export function randomUuid() {
+  return Array.from(Keypair.generate().publicKey.toBytes().slice(0, 16));
+}
+
+// Helper function to request an airdrop to a public key
+export async function requestAirdrop(publicKey: PublicKey, amount = 10_000_000_000) {
+  const signature = await provider.connection.requestAirdrop(publicKey, amount);
+  await provider.connection.confirmTransaction(signature, "confirmed");
+}
+
+// Unix time of the latest confirmed block, as seen by the program's Clock
+export async function clusterTime() {
+  const slot = await provider.connection.getSlot("confirmed");
+  return provider.connection.getBlockTime(slot);
+}
+
+// Polls until the cluster clock has passed `timestamp`
+export async function waitForClusterTime(timestamp: number) {
+  while ((await clusterTime()) <= timestamp) {
+    await new Promise((resolve) => setTimeout(resolve, 400));
+  }
+}
+
+// A freshly funded owner and the PDAs of the store they register
+export function storeFixture() {
+  const owner = Keypair.generate();
+  const storeId = Keypair.generate().publicKey;
+  const store = pda([Buffer.from("store"), storeId.toBuffer()]);
+
+  return {
+    owner,
+    storeId,
+    store,
+    auditLog: pda([Buffer.from("audit_log"), store.toBuffer()]),
+    ownerIndex: pda([Buffer.from("owner_stores"), owner.publicKey.toBuffer()]),
+    catalogIndex: pda([Buffer.from("catalog_index"), store.toBuffer()]),
+    catalogPage: (pageIndex: number) =>
+      pda([Buffer.from("catalog_page"), store.toBuffer(), u32Seed(pageIndex)]),
+    product: (uuid: number[]) =>
+      pda([Buffer.from("product"), store.toBuffer(), Buffer.from(uuid)]),
+    variant: (uuid: number[], variantId: number[]) =>
+      pda([
+        Buffer.from("variant"),
+        pda([Buffer.from("product"), store.toBuffer(), Buffer.from(uuid)]).toBuffer(),
+        Buffer.from(variantId),
+      ]),
+    sku: (barcode: string) =>
+      pda([Buffer.from("sku"), store.toBuffer(), Buffer.from(barcode)]),
+    posSession: (sessionKey: PublicKey) =>
+      pda([Buffer.from("pos_session"), store.toBuffer(), sessionKey.toBuffer()]),
+    escrow: pda([Buffer.from("escrow"), store.toBuffer()]),
+    escrowPolicy: pda([Buffer.from("escrow_policy"), store.toBuffer()]),
+    ownershipTransfer: pda([Buffer.from("ownership_transfer"), store.toBuffer()]),
+    ownerIndexOf: (wallet: PublicKey) =>
+      pda([Buffer.from("owner_stores"), wallet.toBuffer()]),
+    orderCounter: (buyer: PublicKey) =>
+      pda([Buffer.from("order_counter"), store.toBuffer(), buyer.toBuffer()]),
+    receipt: (buyer: PublicKey, orderCount: number) =>
+      pda([Buffer.from("purchase"), store.toBuffer(), buyer.toBuffer(), u64Seed(orderCount)]),
+
+    // Funds the owner and registers the store with its audit log
+    async register(name = "Test Store") {
+      await requestAirdrop(owner.publicKey);
+      await program.methods
+        .registerStore(
+          storeId,
+          name,
+          "Store used by the test suite",
+          "https://example.com/logo.png",
+          { pointsPerDollar: new BN(10), redemptionRate: new BN(100) }
+        )
+        .accounts({
+          store,
+          auditLog: this.auditLog,
+          ownerIndex: this.ownerIndex,
+          authority: owner.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([owner])
+        .rpc();
+    },
+
+    // Registers a product on the first catalog page, optionally under a barcode
+    async registerProduct(uuid: number[], price = 1_000_000, stock = 10, barcode = null) {
+      await program.methods
+        .registerProduct(
+          uuid,
+          new BN(price),
+          new BN(stock),
+          { physical: {} },
+          "https://example.com/product.json",
+          [],
+          barcode
+        )
+        .accounts({
+          store,
+          auditLog: this.auditLog,
+          product: this.product(uuid),
+          catalogIndex: this.catalogIndex,
+          catalogPage: this.catalogPage(0),
+          skuEntry: barcode ? this.sku(barcode) : null,
+          authority: owner.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([owner])
+        .rpc();
+    },
+
+    // Buys `quantity` of one product and returns the receipt address
+    async purchase(buyer: Keypair, uuid: number[], quantity: number, maxAmount: number) {
+      const counter = await program.account.orderCounter.fetchNullable(
+        this.orderCounter(buyer.publicKey)
+      );
+      const receipt = this.receipt(
+        buyer.publicKey,
+        counter ? counter.orderCount.toNumber() : 0
+      );
+      const storeAccount = await program.account.store.fetch(store);
+      await program.methods
+        .purchaseCart([uuid], [], [new BN(quantity)], new BN(maxAmount), new BN(0), new BN(0))
+        .accounts({
+          store,
+          orderCounter: this.orderCounter(buyer.publicKey),
+          receipt,
+          buyer: buyer.publicKey,
+          storeOwner: storeAccount.owner,
+          escrowAccount: this.escrow,
+          loyaltyMintInfo: null,
+          tokenMint: null,
+          tokenAccount: null,
+          tokenProgram: null,
+          systemProgram: SystemProgram.programId,
+        })
+        .remainingAccounts([
+          { pubkey: this.product(uuid), isSigner: false, isWritable: true },
+        ])
+        .signers([buyer])
+        .rpc();
+      return receipt;
+    },
+  };
+}
+
+// The most recently written entry of a ring-buffer audit log
+export function latestEntry(log) {
+  return log.entries[(log.nextIndex + log.entries.length - 1) % log.entries.length];
+}
@@ -0,0 +1,191 @@
+// @ts-nocheck - Disable TypeScript checking for this file due to Anchor-generated type mismatches
+
+import { SystemProgram, Keypair } from "@solana/web3.js";
+import { BN } from "@coral-xyz/anchor";
+import { assert } from "chai";
+import {
+  provider,
+  program,
+  requestAirdrop,
+  clusterTime,
+  waitForClusterTime,
+  randomUuid,
+  storeFixture,
+} from "./helpers";
+
+// POS session keys: owner-issued terminal keys with limited permissions
+describe("sodap pos sessions", () => {
+  const fx = storeFixture();
+  const buyer = Keypair.generate();
+  const uuid = randomUuid();
+
+  // Permission bits, in StorePermission order
+  const ADJUST_STOCK = 1 << 1;
+  const RELEASE_ESCROW = 1 << 2;
+  const ISSUE_REFUNDS = 1 << 3;
+
+  const SOL = 1_000_000_000;
+  const DAILY_CAP = SOL;
+
+  let receipt;
+
+  async function createSession(sessionKey, permissions, expiresAt, dailyCap = DAILY_CAP) {
+    return program.methods
+      .createPosSession(sessionKey, "till 1", permissions, new BN(dailyCap), new BN(expiresAt))
+      .accounts({
+        store: fx.store,
+        auditLog: fx.auditLog,
+        posSession: fx.posSession(sessionKey),
+        owner: fx.owner.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([fx.owner])
+      .rpc();
+  }
+
+  async function refundWithSession(session: Keypair, amount: number) {
+    return program.methods
+      .refundFromEscrow(new BN(amount))
+      .accounts({
+        store: fx.store,
+        auditLog: fx.auditLog,
+        authority: session.publicKey,
+        posSession: fx.posSession(session.publicKey),
+        receipt,
+        buyer: buyer.publicKey,
+        escrowAccount: fx.escrow,
+        escrowPolicy: fx.escrowPolicy,
+      })
+      .signers([session])
+      .rpc();
+  }
+
+  async function expectError(promise, code: string) {
+    try {
+      await promise;
+      assert.fail(`expected ${code}`);
+    } catch (err) {
+      assert.include(err.toString(), code);
+    }
+  }
+
+  before(async () => {
+    await fx.register("POS Store");
+    await requestAirdrop(buyer.publicKey, 20 * SOL);
+
+    // A 4 SOL order in escrow for the sessions to refund against
+    await fx.registerProduct(uuid, 2 * SOL, 10);
+    receipt = await fx.purchase(buyer, uuid, 2, 4 * SOL);
+  });
+
+  it("creates and revokes a session", async () => {
+    const sessionKey = Keypair.generate().publicKey;
+    const expiresAt = (await clusterTime()) + 3600;
+
+    await createSession(sessionKey, ADJUST_STOCK | ISSUE_REFUNDS, expiresAt);
+
+    const session = await program.account.posSession.fetch(fx.posSession(sessionKey));
+    assert.ok(session.store.equals(fx.store));
+    assert.ok(session.sessionKey.equals(sessionKey));
+    assert.equal(session.permissions, ADJUST_STOCK | ISSUE_REFUNDS);
+    assert.equal(session.spentToday.toNumber(), 0);
+    assert.equal(session.expiresAt.toNumber(), expiresAt);
+
+    await program.methods
+      .revokePosSession(sessionKey)
+      .accounts({
+        store: fx.store,
+        auditLog: fx.auditLog,
+        posSession: fx.posSession(sessionKey),
+        owner: fx.owner.publicKey,
+      })
+      .signers([fx.owner])
+      .rpc();
+
+    assert.isNull(await provider.connection.getAccountInfo(fx.posSession(sessionKey)));
+  });
+
+  it("rejects permissions a terminal may not hold", async () => {
+    const sessionKey = Keypair.generate().publicKey;
+    const expiresAt = (await clusterTime()) + 3600;
+    await expectError(createSession(sessionKey, RELEASE_ESCROW, expiresAt), "InvalidParameters");
+  });
+
+  it("rejects sessions longer than seven days", async () => {
+    const sessionKey = Keypair.generate().publicKey;
+    const expiresAt = (await clusterTime()) + 8 * 86400;
+    await expectError(createSession(sessionKey, ADJUST_STOCK, expiresAt), "InvalidParameters");
+  });
+
+  it("refunds through refund_from_escrow and charges the session's daily cap", async () => {
+    const session = Keypair.generate();
+    await createSession(session.publicKey, ISSUE_REFUNDS, (await clusterTime()) + 3600);
+
+    const balanceBefore = await provider.connection.getBalance(buyer.publicKey);
+    await refundWithSession(session, 0.4 * SOL);
+
+    assert.equal(
+      await provider.connection.getBalance(buyer.publicKey),
+      balanceBefore + 0.4 * SOL
+    );
+    const purchase = await program.account.purchase.fetch(receipt);
+    assert.equal(purchase.refunded.toNumber(), 0.4 * SOL);
+    const state = await program.account.posSession.fetch(fx.posSession(session.publicKey));
+    assert.equal(state.spentToday.toNumber(), 0.4 * SOL);
+  });
+
+  it("fails with SessionLimitExceeded once the daily cap is reached", async () => {
+    const session = Keypair.generate();
+    await createSession(session.publicKey, ISSUE_REFUNDS, (await clusterTime()) + 3600);
+
+    await refundWithSession(session, DAILY_CAP);
+    await expectError(refundWithSession(session, 1), "SessionLimitExceeded");
+  });
+
+  it("rejects a session after expires_at", async () => {
+    const session = Keypair.generate();
+    const expiresAt = (await clusterTime()) + 5;
+    await createSession(session.publicKey, ISSUE_REFUNDS, expiresAt);
+    await waitForClusterTime(expiresAt);
+
+    await expectError(refundWithSession(session, 1), "Unauthorized");
+  });
+
+  it("rejects sessions issued before the store changed hands", async () => {
+    const session = Keypair.generate();
+    await createSession(session.publicKey, ISSUE_REFUNDS, (await clusterTime()) + 3600);
+
+    const newOwner = Keypair.generate();
+    await requestAirdrop(newOwner.publicKey);
+    await program.methods
+      .proposeOwnershipTransfer(newOwner.publicKey)
+      .accounts({
+        store: fx.store,
+        auditLog: fx.auditLog,
+        ownershipTransfer: fx.ownershipTransfer,
+        owner: fx.owner.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([fx.owner])
+      .rpc();
+    await program.methods
+      .acceptOwnershipTransfer()
+      .accounts({
+        store: fx.store,
+        auditLog: fx.auditLog,
+        ownershipTransfer: fx.ownershipTransfer,
+        newOwner: newOwner.publicKey,
+        previousOwner: fx.owner.publicKey,
+        previousOwnerIndex: fx.ownerIndex,
+        newOwnerIndex: fx.ownerIndexOf(newOwner.publicKey),
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([newOwner])
+      .rpc();
+
+    const store = await program.account.store.fetch(fx.store);
+    assert.equal(store.sessionEpoch.toNumber(), 1);
+
+    await expectError(refundWithSession(session, 1), "Unauthorized");
+  });
+});